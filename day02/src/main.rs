@@ -0,0 +1,83 @@
+use intcode::symbolic::{Execution, SymbolicError};
+use intcode::{Cpu, Dense, Status};
+use std::convert::TryFrom;
+use std::env;
+use std::io;
+use std::process;
+
+const TARGET: usize = 19690720;
+
+/// `prog[0]` once the program halts, or `None` if it faults.
+fn run(prog: &[usize], noun: usize, verb: usize) -> Option<usize> {
+    let mut cpu = Cpu::with_memory(Dense::new(), prog);
+    cpu.mem_mut()[1] = noun;
+    cpu.mem_mut()[2] = verb;
+    match cpu.run() {
+        Ok(Status::Halted) => Some(cpu.mem()[0]),
+        _ => None,
+    }
+}
+
+/// Solves for `prog[0]` with the noun and verb as unknowns instead of
+/// running every pair. Returns the answers to both parts, where it found
+/// them; a part 2 answer is only taken once a concrete run confirms it.
+fn solve_symbolically(opcodes: &[usize]) -> Result<(Option<usize>, Option<usize>), SymbolicError> {
+    let prog: Vec<i64> = opcodes.iter().map(|&opcode| opcode as i64).collect();
+    let exec = Execution::run(&prog, &[1, 2])?;
+    let output = exec.formula(0);
+    eprintln!("prog[0] = {}", output);
+
+    let part1 = output
+        .eval(&[12, 2])
+        .and_then(|value| usize::try_from(value).ok());
+    let part2 = output
+        .solve(TARGET as i64, 0..=99)
+        .into_iter()
+        .map(|solution| (solution[0] as usize, solution[1] as usize))
+        .find(|&(noun, verb)| run(opcodes, noun, verb) == Some(TARGET))
+        .map(|(noun, verb)| 100 * noun + verb);
+    Ok((part1, part2))
+}
+
+fn main() {
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+    let opcodes: Vec<usize> = input
+        .trim()
+        .split(',')
+        .map(|opcode| opcode.parse().unwrap())
+        .collect();
+
+    // Whatever the symbolic solver doesn't answer is left to running the
+    // program.
+    let (part1, part2) = match env::args().any(|arg| arg == "--symbolic") {
+        true => solve_symbolically(&opcodes).unwrap_or_else(|e| {
+            eprintln!("can't solve symbolically: {}", e);
+            (None, None)
+        }),
+        false => (None, None),
+    };
+
+    match part1.or_else(|| run(&opcodes, 12, 2)) {
+        Some(part1) => println!("part 1: {}", part1),
+        None => {
+            eprintln!("part 1 faults");
+            process::exit(1);
+        }
+    }
+
+    if let Some(part2) = part2 {
+        println!("part 2: {}", part2);
+        return;
+    }
+    for noun in 0..=99 {
+        for verb in 0..=99 {
+            if run(&opcodes, noun, verb) == Some(TARGET) {
+                println!("part 2: {}", 100 * noun + verb);
+                return;
+            }
+        }
+    }
+    eprintln!("no noun and verb give {}", TARGET);
+    process::exit(1);
+}
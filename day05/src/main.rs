@@ -0,0 +1,27 @@
+use intcode::{Cpu, Status};
+use std::io;
+
+fn run(prog: &[i64], input: i64) -> i64 {
+    let mut cpu = Cpu::new(prog);
+    let mut output = 0;
+
+    cpu.push_input(input);
+    while let Status::Output(value) = cpu.run().unwrap() {
+        output = value;
+    }
+
+    output
+}
+
+fn main() {
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+    let opcodes: Vec<i64> = input
+        .trim()
+        .split(',')
+        .map(|opcode| opcode.parse().unwrap())
+        .collect();
+
+    println!("part 1: {}", run(&opcodes, 1));
+    println!("part 2: {}", run(&opcodes, 5));
+}
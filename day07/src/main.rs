@@ -0,0 +1,103 @@
+use intcode::network::{Event, Network, Route, Target, Topology};
+use intcode::{Cpu, Machine};
+use std::env;
+use std::io;
+
+struct Permutations {
+    permutation: Vec<i64>,
+    done: bool,
+}
+
+impl Permutations {
+    fn new(start: i64, end: i64) -> Self {
+        assert!(start <= end);
+        Self {
+            permutation: (start..=end).collect(),
+            done: false,
+        }
+    }
+}
+
+impl Iterator for Permutations {
+    type Item = Vec<i64>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let ret = self.permutation.clone();
+        let mut k = self.permutation.len().checked_sub(2);
+        loop {
+            match k {
+                Some(i) if self.permutation[i] >= self.permutation[i + 1] => k = i.checked_sub(1),
+                _ => break,
+            }
+        }
+        if let Some(k) = k {
+            let x = self
+                .permutation
+                .iter()
+                .enumerate()
+                .rev()
+                .find(|(_, &x)| x > self.permutation[k])
+                .unwrap()
+                .0;
+            self.permutation.swap(x, k);
+            self.permutation[k + 1..].reverse();
+        } else {
+            self.done = true;
+        }
+        Some(ret)
+    }
+}
+
+fn run_amplifiers(prog: &(impl Machine + Clone), phase_settings: &[i64]) -> i64 {
+    let last = phase_settings.len() - 1;
+    let mut topology = Topology::ring(phase_settings.len())
+        .route(last, Route::Send(vec![Target::Node(0), Target::External]));
+    for (i, &ps) in phase_settings.iter().enumerate() {
+        topology = topology.input(i, &[ps]);
+    }
+    topology = topology.input(0, &[0]);
+
+    let mut network = Network::new(topology, prog).unwrap();
+    let mut signal = None;
+    while let Event::Output { value, .. } = network.run().unwrap() {
+        signal = Some(value);
+    }
+
+    signal.expect("no output")
+}
+
+fn highest_signal(
+    prog: &(impl Machine + Clone),
+    phase_settings_start: i64,
+    phase_settings_end: i64,
+) -> i64 {
+    Permutations::new(phase_settings_start, phase_settings_end)
+        .map(|phase_settings| run_amplifiers(prog, &phase_settings))
+        .max()
+        .unwrap()
+}
+
+fn main() {
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+    let prog: Vec<i64> = input
+        .trim()
+        .split(',')
+        .map(|opcode| opcode.parse().unwrap())
+        .collect();
+
+    match env::args().nth(1).as_deref() {
+        #[cfg(all(feature = "jit", target_arch = "x86_64", target_os = "linux"))]
+        Some("--jit") => solve(&intcode::jit::Jit::new(&prog)),
+        _ => solve(&Cpu::new(&prog)),
+    }
+}
+
+fn solve(prog: &(impl Machine + Clone)) {
+    println!("part 1: {}", highest_signal(prog, 0, 4));
+    println!("part 2: {}", highest_signal(prog, 5, 9));
+}
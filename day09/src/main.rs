@@ -0,0 +1,33 @@
+use intcode::{BigInt, Cell, Cpu, Dense, Machine};
+use std::env;
+use std::fmt::Debug;
+use std::io;
+
+fn run<C: Cell>(program: &[C], input: i64) -> C {
+    let mut cpu = Cpu::with_memory(Dense::new(), program);
+    let mut output = Vec::new();
+
+    cpu.run_io(C::from_i64(input), &mut output).unwrap();
+
+    output.pop().unwrap_or_default()
+}
+
+fn solve<C: Cell>(input: &str)
+where
+    C::Err: Debug,
+{
+    let opcodes: Vec<C> = intcode::parse_program(input).unwrap();
+
+    println!("part 1: {}", run(&opcodes, 1));
+    println!("part 2: {}", run(&opcodes, 2));
+}
+
+fn main() {
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+
+    match env::args().nth(1).as_deref() {
+        Some("--bigint") => solve::<BigInt>(&input),
+        _ => solve::<i64>(&input),
+    }
+}
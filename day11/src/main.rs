@@ -0,0 +1,87 @@
+use intcode::{Cpu, Status};
+use std::collections::HashMap;
+use std::io;
+use std::ops::RangeInclusive;
+
+fn paint(program: &[i64], starting_color: i64) -> HashMap<(i32, i32), i64> {
+    let mut panels = HashMap::new();
+    let mut x = 0;
+    let mut y = 0;
+    let mut direction = 0;
+    let mut cpu = Cpu::new(program);
+
+    let directions = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+    *panels.entry((x, y)).or_insert(0) = starting_color;
+
+    loop {
+        let color = match cpu.run().unwrap() {
+            Status::Output(color) => color,
+            Status::NeedsInput => {
+                cpu.push_input(*panels.get(&(x, y)).unwrap_or(&0));
+                continue;
+            }
+            Status::Halted => break,
+        };
+
+        let entry = panels.entry((x, y)).or_insert(0);
+        *entry = color;
+
+        if let Some(d) = cpu.run().unwrap().output() {
+            if d == 0 {
+                if direction == 0 {
+                    direction = directions.len() - 1;
+                } else {
+                    direction -= 1;
+                }
+            } else {
+                direction += 1;
+            }
+            direction %= directions.len();
+
+            x += directions[direction].0;
+            y += directions[direction].1;
+        } else {
+            panic!("no direction given by the program");
+        }
+    }
+
+    panels
+}
+
+fn coord_range<F>(panels: &HashMap<(i32, i32), i64>, coord: F) -> RangeInclusive<i32>
+where
+    F: Fn(&(i32, i32)) -> i32,
+{
+    let min = panels.keys().map(&coord).min().unwrap();
+    let max = panels.keys().map(&coord).max().unwrap();
+    min..=max
+}
+
+fn main() {
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+    let program: Vec<i64> = input
+        .trim()
+        .split(',')
+        .map(|opcode| opcode.parse().unwrap())
+        .collect();
+
+    println!("part 1: {}", paint(&program, 0).len());
+
+    let panels = paint(&program, 1);
+
+    println!("part 2:");
+    for y in coord_range(&panels, |&(_, y)| y) {
+        for x in coord_range(&panels, |&(x, _)| x) {
+            print!(
+                "{}",
+                match panels.get(&(x, y)) {
+                    Some(1) => '#',
+                    _ => ' ',
+                }
+            );
+        }
+        println!();
+    }
+}
@@ -0,0 +1,135 @@
+use intcode::record::Recorder;
+use intcode::{Cpu, Machine, Status};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+const BLOCK: i64 = 2;
+const PADDLE: i64 = 3;
+const BALL: i64 = 4;
+
+/// A machine the arcade can insert quarters into.
+trait Console: Machine {
+    fn poke(&mut self, addr: usize, value: i64);
+}
+
+impl Console for Cpu {
+    fn poke(&mut self, addr: usize, value: i64) {
+        self.mem_mut()[addr] = value;
+    }
+}
+
+impl Console for Recorder {
+    fn poke(&mut self, addr: usize, value: i64) {
+        Recorder::poke(self, addr, value)
+    }
+}
+
+struct Arcade<M> {
+    cpu: M,
+    score: i64,
+    screen: HashMap<(i64, i64), i64>,
+}
+
+impl<M: Console> Arcade<M> {
+    fn new(cpu: M) -> Self {
+        Self {
+            cpu,
+            score: 0,
+            screen: HashMap::new(),
+        }
+    }
+
+    fn run_until_exit(&mut self) {
+        loop {
+            if let Status::Halted = self.run(Some(0)) {
+                break;
+            }
+        }
+    }
+
+    fn run(&mut self, input: Option<i64>) -> Status {
+        let mut output = Vec::new();
+        let status = self.cpu.run_io(input, &mut output).unwrap();
+
+        for tile in output.chunks(3) {
+            match *tile {
+                [-1, 0, score] => self.score = score,
+                [x, y, tile] => {
+                    self.screen.insert((x, y), tile);
+                }
+                _ => panic!("incomplete tile: {:?}", tile),
+            }
+        }
+
+        status
+    }
+
+    fn winning_score(&mut self) -> i64 {
+        let mut input = 0;
+
+        self.cpu.poke(0, 2);
+
+        loop {
+            if let Status::Halted = self.run(Some(input)) {
+                return self.score;
+            }
+
+            let (pad_x, _) = find_tile_position(PADDLE, &self.screen).expect("no paddle");
+            let (ball_dst_x, _) = find_tile_position(BALL, &self.screen).expect("no ball");
+            input = match pad_x.cmp(&ball_dst_x) {
+                Ordering::Less => 1,
+                Ordering::Equal => 0,
+                Ordering::Greater => -1,
+            };
+        }
+    }
+}
+
+fn find_tile_position(tile: i64, screen: &HashMap<(i64, i64), i64>) -> Option<(i64, i64)> {
+    screen.iter().find(|(_, &t)| t == tile).map(|(&p, _)| p)
+}
+
+fn main() {
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+    let program: Vec<i64> = input
+        .trim()
+        .split(',')
+        .map(|opcode| opcode.parse().unwrap())
+        .collect();
+
+    let mut arcade = Arcade::new(Cpu::new(&program));
+    arcade.run_until_exit();
+    println!(
+        "part 1: {}",
+        arcade
+            .screen
+            .iter()
+            .filter(|(_, &tile)| tile == BLOCK)
+            .count()
+    );
+
+    // With `--record`, the paddle moves of part 2 are saved to check with
+    // `intcode-replay`.
+    match env::args().skip_while(|arg| arg != "--record").nth(1) {
+        Some(path) => {
+            let mut arcade = Arcade::new(Recorder::new(Cpu::new(&program)));
+            println!("part 2: {}", arcade.winning_score());
+            let saved = File::create(&path).and_then(|file| {
+                let mut file = BufWriter::new(file);
+                arcade.cpu.log().save(&mut file)?;
+                file.flush()
+            });
+            if let Err(e) = saved {
+                eprintln!("error writing {}: {}", path, e);
+            }
+        }
+        None => {
+            let mut arcade = Arcade::new(Cpu::new(&program));
+            println!("part 2: {}", arcade.winning_score());
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use intcode::Cpu;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::io;
+
+enum PositionType {
+    Wall,
+    Empty,
+    OxygenSystem,
+}
+
+type Position = (i32, i32);
+
+fn adjacent_positions(p: Position) -> impl Iterator<Item = (Position, usize)> {
+    [(0, 1), (0, -1), (-1, 0), (1, 0)]
+        .iter()
+        .enumerate()
+        .map(move |(i, d)| ((p.0 + d.0, p.1 + d.1), i + 1))
+}
+
+fn get_area_map(program: &[i64]) -> HashMap<Position, PositionType> {
+    let mut area_map = HashMap::new();
+
+    let mut q = VecDeque::new();
+    let mut discovered = HashSet::new();
+
+    q.push_back((Cpu::new(program), (0, 0)));
+    discovered.insert((0, 0));
+
+    while !q.is_empty() {
+        let (cpu, pos) = q.pop_front().unwrap();
+        for (new_pos, direction) in adjacent_positions(pos) {
+            if discovered.contains(&new_pos) {
+                continue;
+            }
+            discovered.insert(new_pos);
+
+            let mut new_cpu = cpu.clone();
+            new_cpu.push_input(direction as i64);
+            let pt = new_cpu.run().unwrap().output().expect("no position type");
+            area_map.insert(
+                new_pos,
+                match pt {
+                    0 => PositionType::Wall,
+                    1 => {
+                        q.push_back((new_cpu, new_pos));
+                        PositionType::Empty
+                    }
+                    2 => PositionType::OxygenSystem,
+                    _ => panic!("invalid position type: {}", pt),
+                },
+            );
+        }
+    }
+
+    area_map
+}
+
+fn distance_map(
+    from: &Position,
+    map: &HashMap<Position, PositionType>,
+) -> HashMap<Position, usize> {
+    let mut dist_map = HashMap::new();
+
+    let mut q = VecDeque::new();
+    let mut discovered = HashSet::new();
+
+    q.push_back((*from, 0));
+    discovered.insert(*from);
+
+    while !q.is_empty() {
+        let (pos, dist) = q.pop_front().unwrap();
+        dist_map.insert(pos, dist);
+
+        for (new_pos, _) in adjacent_positions(pos) {
+            if discovered.contains(&new_pos) {
+                continue;
+            }
+            discovered.insert(new_pos);
+
+            if let PositionType::Empty | PositionType::OxygenSystem =
+                map.get(&new_pos).unwrap_or(&PositionType::Wall)
+            {
+                q.push_back((new_pos, dist + 1));
+            }
+        }
+    }
+
+    dist_map
+}
+
+fn main() {
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+    let program: Vec<i64> = input
+        .trim()
+        .split(',')
+        .map(|opcode| opcode.parse().unwrap())
+        .collect();
+
+    let map = get_area_map(&program);
+    let oxygen_system_position = map
+        .iter()
+        .find(|(_, pt)| matches!(pt, PositionType::OxygenSystem))
+        .expect("no oxygen")
+        .0;
+
+    println!(
+        "part 1: {}",
+        distance_map(&(0, 0), &map)
+            .get(oxygen_system_position)
+            .unwrap()
+    );
+
+    println!(
+        "part 2: {}",
+        distance_map(oxygen_system_position, &map)
+            .values()
+            .max()
+            .unwrap()
+    );
+}
@@ -0,0 +1,224 @@
+use intcode::ascii::Session;
+use intcode::Cpu;
+use std::cmp;
+use std::fmt;
+use std::io;
+
+fn build_map(program: &[i64]) -> Vec<Vec<char>> {
+    let camera = Session::new(Cpu::new(program)).read_all().unwrap();
+    assert!(camera.values.is_empty(), "invalid {:?}", camera.values);
+
+    camera
+        .text
+        .lines()
+        .filter(|row| !row.is_empty())
+        .map(|row| {
+            row.chars()
+                .inspect(|&c| assert!("#.^>v<".contains(c), "invalid {}", c))
+                .collect()
+        })
+        .collect()
+}
+
+const UP: (i32, i32) = (0, -1);
+const RIGHT: (i32, i32) = (1, 0);
+const DOWN: (i32, i32) = (0, 1);
+const LEFT: (i32, i32) = (-1, 0);
+
+fn within_bounds(x: i32, y: i32, map: &[Vec<char>]) -> bool {
+    y >= 0 && (y as usize) < map.len() && x >= 0 && (x as usize) < map[y as usize].len()
+}
+
+fn is_scaffold(x: i32, y: i32, map: &[Vec<char>]) -> bool {
+    within_bounds(x, y, map) && map[y as usize][x as usize] != '.'
+}
+
+fn is_intersection(x: i32, y: i32, map: &[Vec<char>]) -> bool {
+    [UP, RIGHT, DOWN, LEFT]
+        .iter()
+        .filter(|(dx, dy)| is_scaffold(x + dx, y + dy, map))
+        .count()
+        > 2
+}
+
+fn intersections_alignment_parameters_sum(map: &[Vec<char>]) -> usize {
+    let mut alignment_param_sum = 0;
+
+    for y in 0..map.len() {
+        for x in 0..map[y].len() {
+            if map[y][x] == '#' && is_intersection(x as i32, y as i32, map) {
+                alignment_param_sum += x * y;
+            }
+        }
+    }
+
+    alignment_param_sum
+}
+
+#[derive(Debug, PartialEq)]
+enum Movement {
+    MoveForward(usize),
+    Turn(char),
+}
+
+impl fmt::Display for Movement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Movement::MoveForward(len) => write!(f, "{}", len),
+            Movement::Turn(c) => write!(f, "{}", c),
+        }
+    }
+}
+
+fn starting_position(map: &[Vec<char>]) -> (i32, i32) {
+    let p = map
+        .iter()
+        .flatten()
+        .enumerate()
+        .find(|(_, &c)| c != '.' && c != '#')
+        .unwrap()
+        .0;
+    ((p % map[0].len()) as i32, (p / map[0].len()) as i32)
+}
+
+fn build_path(map: &[Vec<char>]) -> Vec<Movement> {
+    let (mut x, mut y) = starting_position(map);
+    let mut dir = match map[y as usize][x as usize] {
+        '^' => UP,
+        '>' => RIGHT,
+        'v' => DOWN,
+        '<' => LEFT,
+        c => panic!("invalid direction robot char: {}", c),
+    };
+
+    let mut path = Vec::new();
+    let mut straight_len = 0;
+
+    loop {
+        let (nx, ny) = (x + dir.0, y + dir.1);
+        if is_scaffold(nx, ny, map) {
+            x = nx;
+            y = ny;
+            straight_len += 1;
+            continue;
+        }
+
+        if straight_len > 0 {
+            path.push(Movement::MoveForward(straight_len));
+            straight_len = 0;
+        }
+
+        let dir_left = match dir {
+            UP => LEFT,
+            RIGHT => UP,
+            DOWN => RIGHT,
+            LEFT => DOWN,
+            _ => panic!("invalid dir"),
+        };
+        let dir_right = match dir {
+            UP => RIGHT,
+            RIGHT => DOWN,
+            DOWN => LEFT,
+            LEFT => UP,
+            _ => panic!("invalid dir"),
+        };
+
+        let left = (x + dir_left.0, y + dir_left.1);
+        let right = (x + dir_right.0, y + dir_right.1);
+
+        if is_scaffold(left.0, left.1, map) && is_scaffold(right.0, right.1, map) {
+            panic!("T intersection");
+        } else if is_scaffold(left.0, left.1, map) {
+            dir = dir_left;
+            path.push(Movement::Turn('L'));
+        } else if is_scaffold(right.0, right.1, map) {
+            dir = dir_right;
+            path.push(Movement::Turn('R'));
+        } else {
+            break;
+        }
+    }
+
+    path
+}
+
+fn solve<'a>(
+    cur_func: usize,
+    path: &'a [Movement],
+    main: &mut String,
+    functions: &mut [&'a [Movement]; 3],
+) -> bool {
+    if main.len() > 20 {
+        return false;
+    }
+
+    for i in 0..cur_func {
+        let f = functions[i];
+        if path.len() >= f.len() && &path[0..f.len()] == f {
+            main.push((b'A' + i as u8) as char);
+            if solve(cur_func, &path[f.len()..], main, functions) {
+                return true;
+            }
+            main.pop();
+            return false;
+        }
+    }
+
+    if cur_func == 3 {
+        return path.is_empty();
+    }
+
+    for len in 1..cmp::min(path.len(), 20) {
+        functions[cur_func] = &path[0..len];
+        if solve(cur_func + 1, path, main, functions) {
+            return true;
+        }
+    }
+
+    false
+}
+
+fn function_str(func: &[Movement]) -> String {
+    let moves: Vec<_> = func.iter().map(Movement::to_string).collect();
+    moves.join(",")
+}
+
+fn collect_space_dust(program: &[i64], main_routine: &str, functions: &[&[Movement]; 3]) -> i64 {
+    let mut cpu = Cpu::new(program);
+    cpu.mem_mut()[0] = 2;
+    let mut robot = Session::new(cpu);
+    robot.read_all().unwrap();
+
+    let calls: Vec<_> = main_routine.chars().map(String::from).collect();
+    robot.command(&calls.join(",")).unwrap();
+    for func in functions {
+        robot.command(&function_str(func)).unwrap();
+    }
+
+    let reply = robot.command("n").unwrap();
+    *reply.values.last().expect("no dust collected")
+}
+
+fn main() {
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+    let program: Vec<i64> = input
+        .trim()
+        .split(',')
+        .map(|opcode| opcode.parse().unwrap())
+        .collect();
+
+    let map = build_map(&program);
+
+    println!("part 1: {}", intersections_alignment_parameters_sum(&map));
+
+    let path = build_path(&map);
+    let mut functions: [&[Movement]; 3] = [&path, &path, &path];
+    let mut main = String::new();
+    assert!(solve(0, &path, &mut main, &mut functions));
+
+    println!(
+        "part 2: {}",
+        collect_space_dust(&program, &main, &functions)
+    );
+}
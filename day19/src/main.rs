@@ -0,0 +1,81 @@
+use intcode::{Cpu, Machine};
+use std::env;
+use std::io;
+
+/// The drone program compiled from `input` by the build script.
+#[allow(clippy::all, unused)]
+mod drone {
+    include!(concat!(env!("OUT_DIR"), "/drone.rs"));
+}
+
+fn pulled(x: i64, y: i64, drone: &(impl Machine + Clone)) -> bool {
+    let mut cpu = drone.clone();
+    cpu.push_input(x);
+    cpu.push_input(y);
+    match cpu.run().unwrap().output() {
+        Some(0) => false,
+        Some(1) => true,
+        output => panic!("invalid ouput {:?}", output),
+    }
+}
+
+fn main() {
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+    let program: Vec<i64> = input
+        .trim()
+        .split(',')
+        .map(|opcode| opcode.parse().unwrap())
+        .collect();
+
+    match env::args().nth(1).as_deref() {
+        Some("--aot") => {
+            assert!(
+                program == drone::PROGRAM,
+                "--aot only runs the input it was built from"
+            );
+            solve(&drone::new());
+        }
+        #[cfg(all(feature = "jit", target_arch = "x86_64", target_os = "linux"))]
+        Some("--jit") => solve(&intcode::jit::Jit::new(&program)),
+        _ => solve(&Cpu::new(&program)),
+    }
+}
+
+fn solve(drone: &(impl Machine + Clone)) {
+    println!(
+        "part 1: {}",
+        (0..50)
+            .map(|y| (0..50)
+                .map(|x| { pulled(x, y, drone) as usize })
+                .sum::<usize>())
+            .sum::<usize>()
+    );
+
+    const SIZE: i64 = 100;
+
+    let mut prev_y = 0;
+
+    for x in 0.. {
+        let mut y = prev_y;
+        while !pulled(x, y, drone) {
+            y += 1;
+            if y - prev_y > 1000 {
+                break;
+            }
+        }
+        if y - prev_y > 1000 {
+            continue;
+        }
+        prev_y = y;
+
+        if x >= SIZE
+            && pulled(x - SIZE + 1, y, drone)
+            && pulled(x, y + SIZE - 1, drone)
+            && pulled(x - SIZE + 1, y + SIZE - 1, drone)
+        {
+            println!("part 2: {}", (x - SIZE + 1) * 10_000 + y);
+            break;
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use intcode::ascii::Session;
+use intcode::Cpu;
+use std::io;
+
+fn run_springscript(springscript_program: &[&str], program: &[i64]) -> Option<i64> {
+    let mut droid = Session::new(Cpu::new(program));
+    droid.read_all().unwrap();
+
+    for instr in springscript_program {
+        droid.send_line(instr);
+    }
+
+    droid.read_all().unwrap().values.first().copied()
+}
+
+const PART1_SC: [&str; 6] = ["NOT C J", "AND D J", "NOT A T", "AND D T", "OR T J", "WALK"];
+
+const PART2_SC: [&str; 12] = [
+    "NOT A J", "NOT B T", "OR T J", "NOT C T", "OR T J", "NOT D T", "NOT T T", "AND T J",
+    "AND E T", "OR H T", "AND T J", "RUN",
+];
+
+fn main() {
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+    let program: Vec<i64> = input
+        .trim()
+        .split(',')
+        .map(|opcode| opcode.parse().unwrap())
+        .collect();
+
+    println!(
+        "part 1: {}",
+        run_springscript(&PART1_SC, &program).expect("fail")
+    );
+    println!(
+        "part 2: {}",
+        run_springscript(&PART2_SC, &program).expect("fail")
+    );
+}
@@ -0,0 +1,12 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// Transpiles the puzzle input for the `--aot` backend.
+fn main() {
+    println!("cargo:rerun-if-changed=input");
+    let input = fs::read_to_string("input").unwrap();
+    let program = intcode::parse_program(&input).unwrap();
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("nic.rs");
+    fs::write(out, intcode::aot::transpile(&program)).unwrap();
+}
@@ -0,0 +1,80 @@
+use intcode::network::{Event, Network, OnEmpty, Scheduler, Threaded, Topology};
+use intcode::{Cpu, Machine};
+use std::collections::HashSet;
+use std::env;
+use std::io;
+
+/// The NIC program compiled from `input` by the build script.
+#[allow(clippy::all, unused)]
+mod nic {
+    include!(concat!(env!("OUT_DIR"), "/nic.rs"));
+}
+
+/// How many empty polls in a row a NIC must make before the threaded
+/// network counts it as idle.
+const IDLE_POLLS: usize = 100;
+
+fn topology(n: usize) -> Topology {
+    let mut topology = Topology::bus(n, 3).on_empty(OnEmpty::Value(-1));
+    for addr in 0..n {
+        topology = topology.input(addr, &[addr as i64]);
+    }
+    topology
+}
+
+fn run_network_computers<T>(n: usize, nic: T, threads: bool) -> (i64, i64)
+where
+    T: Machine + Clone + Send + 'static,
+{
+    if threads {
+        run_nat(Threaded::new(topology(n), &nic, IDLE_POLLS).unwrap())
+    } else {
+        run_nat(Network::new(topology(n), &nic).unwrap())
+    }
+}
+
+fn run_nat(mut network: impl Scheduler) -> (i64, i64) {
+    let mut nat_received = Vec::new();
+    let mut nat_sent = HashSet::new();
+
+    loop {
+        match network.run().unwrap() {
+            Event::Packet {
+                addr: 255, words, ..
+            } => nat_received.push((words[0], words[1])),
+            Event::Idle => {
+                if let Some(&nat_packet) = nat_received.last() {
+                    network.send(0, &[nat_packet.0, nat_packet.1]);
+                    if !nat_sent.insert(nat_packet) {
+                        return (nat_received[0].1, nat_packet.1);
+                    }
+                }
+            }
+            Event::Halted => panic!("every NIC halted"),
+            event => panic!("unexpected {:?}", event),
+        }
+    }
+}
+
+fn main() {
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+    let program: Vec<i64> = input
+        .trim()
+        .split(',')
+        .map(|opcode| opcode.parse().unwrap())
+        .collect();
+
+    let threads = env::args().any(|arg| arg == "--threads");
+    let (part1, part2) = if env::args().any(|arg| arg == "--aot") {
+        assert!(
+            program == nic::PROGRAM,
+            "--aot only runs the input it was built from"
+        );
+        run_network_computers(50, nic::new(), threads)
+    } else {
+        run_network_computers(50, Cpu::new(&program), threads)
+    };
+    println!("part 1: {}", part1);
+    println!("part 2: {}", part2);
+}
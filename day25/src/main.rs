@@ -0,0 +1,176 @@
+use intcode::ascii::{Reply, Session};
+use intcode::record::Recorder;
+use intcode::snapshot::SnapshotError;
+use intcode::{Cpu, IntcodeError, Machine, Status};
+use std::env;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+
+/// The droid's CPU, recorded only when `--record` is going to save the
+/// session for `intcode-replay`.
+enum Console {
+    Plain(Cpu),
+    Recorded(Recorder),
+}
+
+impl Console {
+    fn new(cpu: Cpu, record: bool) -> Self {
+        match record {
+            true => Console::Recorded(Recorder::new(cpu)),
+            false => Console::Plain(cpu),
+        }
+    }
+
+    fn cpu(&self) -> &Cpu {
+        match self {
+            Console::Plain(cpu) => cpu,
+            Console::Recorded(recorder) => recorder.cpu(),
+        }
+    }
+}
+
+impl Machine for Console {
+    fn push_input(&mut self, value: i64) {
+        match self {
+            Console::Plain(cpu) => cpu.push_input(value),
+            Console::Recorded(recorder) => recorder.push_input(value),
+        }
+    }
+
+    fn run(&mut self) -> Result<Status, IntcodeError> {
+        match self {
+            Console::Plain(cpu) => cpu.run(),
+            Console::Recorded(recorder) => recorder.run(),
+        }
+    }
+}
+
+type Droid = Session<Console>;
+
+fn print_reply(reply: Reply) {
+    print!("{}", reply.text);
+    for value in reply.values {
+        println!("{}", value);
+    }
+}
+
+fn get_inventory(droid: &mut Droid) -> Vec<String> {
+    let reply = droid.command("inv").unwrap();
+    reply
+        .text
+        .lines()
+        .filter_map(|line| line.strip_prefix("- "))
+        .map(String::from)
+        .collect()
+}
+
+fn drop(item: &str, droid: &mut Droid) {
+    print_reply(droid.command(&format!("drop {}", item)).unwrap());
+}
+
+fn take(item: &str, droid: &mut Droid) {
+    print_reply(droid.command(&format!("take {}", item)).unwrap());
+}
+
+fn try_command(cmd: &str, droid: &mut Droid) -> bool {
+    print_reply(droid.command(cmd).unwrap());
+    droid.is_halted()
+}
+
+fn solve_helper(cur: usize, n: usize, items: &mut [String], droid: &mut Droid) -> bool {
+    if cur == n {
+        println!("TRYING WITH:");
+        print_reply(droid.command("inv").unwrap());
+
+        return try_command("east", droid);
+    }
+
+    for i in cur..items.len() {
+        items.swap(cur, i);
+        take(&items[cur], droid);
+
+        if solve_helper(cur + 1, n, items, droid) {
+            return true;
+        }
+
+        drop(&items[cur], droid);
+        items.swap(cur, i);
+    }
+
+    false
+}
+
+fn solve(droid: &mut Droid) {
+    let mut inv = get_inventory(droid);
+    println!("inv = {:?}", inv);
+
+    inv.iter().for_each(|item| drop(item, droid));
+
+    for n in 1..=inv.len() {
+        if solve_helper(0, n, &mut inv, droid) {
+            break;
+        }
+    }
+}
+
+fn main() {
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+    let program: Vec<i64> = input
+        .trim()
+        .split(',')
+        .map(|opcode| opcode.parse().unwrap())
+        .collect();
+
+    let record = env::args().skip_while(|arg| arg != "--record").nth(1);
+    let mut droid = Session::new(Console::new(Cpu::new(&program), record.is_some()));
+    loop {
+        print_reply(droid.read_all().unwrap());
+
+        let mut input = String::new();
+        if io::stdin().read_line(&mut input).unwrap() == 0 {
+            break;
+        }
+
+        if input == "solve\n" {
+            solve(&mut droid);
+            break;
+        }
+
+        if let Some(path) = input.strip_prefix("save ") {
+            match File::create(path.trim()).and_then(|mut f| droid.machine().cpu().save(&mut f)) {
+                Ok(()) => println!("saved to {}", path.trim()),
+                Err(e) => println!("save failed: {}", e),
+            }
+            continue;
+        }
+
+        if let Some(path) = input.strip_prefix("load ") {
+            match File::open(path.trim())
+                .map_err(SnapshotError::from)
+                .and_then(|f| Cpu::restore(BufReader::new(f)))
+            {
+                // The recording starts over, to replay from the snapshot.
+                Ok(restored) => {
+                    droid = Session::new(Console::new(restored, record.is_some()));
+                    println!("loaded {}", path.trim());
+                }
+                Err(e) => println!("load failed: {}", e),
+            }
+            continue;
+        }
+
+        droid.send_line(input.trim_end_matches('\n'));
+    }
+
+    if let (Some(path), Console::Recorded(recorder)) = (record, droid.machine()) {
+        let saved = File::create(&path).and_then(|file| {
+            let mut file = BufWriter::new(file);
+            recorder.log().save(&mut file)?;
+            file.flush()
+        });
+        if let Err(e) = saved {
+            eprintln!("error writing {}: {}", path, e);
+        }
+    }
+}
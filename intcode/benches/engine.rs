@@ -0,0 +1,139 @@
+//! Compares the execution engines on the two heaviest Intcode days: the
+//! uncached reference interpreter, the cached one behind `Cpu::run` and,
+//! with the `jit` feature, the JIT. Run with `cargo bench`, adding
+//! `--features jit` for the JIT.
+
+use intcode::{parse_program, Cpu, IntcodeError, Machine, Status};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// `Cpu::run_uncached` behind the `Machine` interface.
+#[derive(Clone)]
+struct Uncached(Cpu);
+
+impl Machine for Uncached {
+    fn push_input(&mut self, value: i64) {
+        self.0.push_input(value);
+    }
+
+    fn run(&mut self) -> Result<Status, IntcodeError> {
+        self.0.run_uncached()
+    }
+}
+
+/// Scans the day 19 tractor beam over a 100x100 grid, one fresh drone per
+/// point.
+fn day19(drone: &(impl Machine + Clone)) -> usize {
+    let mut pulled = 0;
+    for y in 0..100 {
+        for x in 0..100 {
+            let mut cpu = drone.clone();
+            cpu.push_input(x);
+            cpu.push_input(y);
+            if cpu.run().unwrap() == Status::Output(1) {
+                pulled += 1;
+            }
+        }
+    }
+    pulled
+}
+
+/// Runs the day 23 network until the NAT sends the same y twice in a row.
+fn day23(nic: &(impl Machine + Clone)) -> i64 {
+    let mut cpus: Vec<_> = (0..50).map(|_| nic.clone()).collect();
+    let mut queues: Vec<VecDeque<i64>> = (0..50)
+        .map(|addr| Some(addr).into_iter().collect())
+        .collect();
+    let mut nat = None;
+    let mut last_y = None;
+
+    loop {
+        let mut idle = true;
+        for addr in 0..cpus.len() {
+            match cpus[addr].run().unwrap() {
+                Status::Output(dest) => {
+                    let x = cpus[addr].run().unwrap().output().unwrap();
+                    let y = cpus[addr].run().unwrap().output().unwrap();
+                    idle = false;
+                    if dest == 255 {
+                        nat = Some((x, y));
+                    } else {
+                        queues[dest as usize].extend(&[x, y]);
+                    }
+                }
+                Status::NeedsInput => {
+                    idle &= queues[addr].is_empty();
+                    let input = queues[addr].pop_front().unwrap_or(-1);
+                    cpus[addr].push_input(input);
+                }
+                Status::Halted => {}
+            }
+        }
+        if let (true, Some((x, y))) = (idle, nat) {
+            if last_y == Some(y) {
+                return y;
+            }
+            last_y = Some(y);
+            queues[0].extend(&[x, y]);
+        }
+    }
+}
+
+struct Bench<T> {
+    name: &'static str,
+    iters: u32,
+    baseline: Option<(Duration, T)>,
+}
+
+impl<T: PartialEq + std::fmt::Debug> Bench<T> {
+    fn new(name: &'static str, iters: u32) -> Self {
+        Self {
+            name,
+            iters,
+            baseline: None,
+        }
+    }
+
+    fn time(&mut self, engine: &str, mut f: impl FnMut() -> T) {
+        let start = Instant::now();
+        let mut result = f();
+        for _ in 1..self.iters {
+            result = f();
+        }
+        let per_iter = start.elapsed() / self.iters;
+        match &self.baseline {
+            None => {
+                println!("{:<6} {:<9} {:>10.3?}", self.name, engine, per_iter);
+                self.baseline = Some((per_iter, result));
+            }
+            Some((base, expected)) => {
+                assert_eq!(&result, expected, "{} disagrees on {}", engine, self.name);
+                println!(
+                    "{:<6} {:<9} {:>10.3?}  ({:.2}x)",
+                    self.name,
+                    engine,
+                    per_iter,
+                    base.as_secs_f64() / per_iter.as_secs_f64()
+                );
+            }
+        }
+    }
+}
+
+fn main() {
+    let load = |path| parse_program(&std::fs::read_to_string(path).unwrap()).unwrap();
+    let drone = load(concat!(env!("CARGO_MANIFEST_DIR"), "/../day19/input"));
+    let nic = load(concat!(env!("CARGO_MANIFEST_DIR"), "/../day23/input"));
+
+    let mut bench = Bench::new("day19", 10);
+    bench.time("uncached", || day19(&Uncached(Cpu::new(&drone))));
+    bench.time("cached", || day19(&Cpu::new(&drone)));
+    #[cfg(all(feature = "jit", target_arch = "x86_64", target_os = "linux"))]
+    bench.time("jit", || day19(&intcode::jit::Jit::new(&drone)));
+
+    let mut bench = Bench::new("day23", 20);
+    bench.time("uncached", || day23(&Uncached(Cpu::new(&nic))));
+    bench.time("cached", || day23(&Cpu::new(&nic)));
+    #[cfg(all(feature = "jit", target_arch = "x86_64", target_os = "linux"))]
+    bench.time("jit", || day23(&intcode::jit::Jit::new(&nic)));
+}
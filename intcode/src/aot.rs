@@ -0,0 +1,424 @@
+//! Ahead-of-time translation of Intcode programs to Rust.
+//!
+//! `transpile` turns a program into a Rust module with one `match` arm per
+//! instruction it finds. The module's `new()` returns a `Native` machine,
+//! which runs the generated code and lets the interpreter execute any
+//! instruction it has no arm for, whose arm a write into the code made
+//! stale, or that faults or overflows.
+
+use crate::cache::DecodeCache;
+use crate::cpu::{Cpu, Machine, Status};
+use crate::disasm::{disassemble, disassemble_from, Instruction};
+use crate::error::IntcodeError;
+use crate::memory::{Dense, Memory};
+use crate::observer::Observer;
+use crate::opcode::{Mode, Opcode};
+use crate::overflow::Overflow;
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::fmt;
+use std::sync::Arc;
+
+/// Generated code for one program: runs from the current pc until an
+/// input, output or halt, or returns `None` with pc on an instruction it
+/// can't run.
+pub type Compiled = fn(&mut Native) -> Option<Status>;
+
+/// Marks the arms that relied on the old value of `addr` as stale.
+fn retire(code: &[bool], stale: &mut Vec<bool>, addr: usize) {
+    if code.get(addr) == Some(&true) {
+        if stale.is_empty() {
+            *stale = vec![false; code.len()];
+        }
+        stale[addr.saturating_sub(3)..=addr].fill(true);
+    }
+}
+
+/// Catches interpreted writes into code the generated arms rely on.
+struct CodeWatch<'a> {
+    code: &'a [bool],
+    stale: &'a mut Vec<bool>,
+}
+
+impl Observer for CodeWatch<'_> {
+    fn write(&mut self, addr: usize, old: i64, new: i64) {
+        if old != new {
+            retire(self.code, self.stale, addr);
+        }
+    }
+}
+
+/// A machine running transpiled code, with the same resumable API as
+/// `Cpu`.
+#[derive(Clone)]
+pub struct Native {
+    cpu: Cpu,
+    code: Arc<[bool]>,
+    /// Empty until the first write into the code.
+    stale: Vec<bool>,
+    /// Instructions left before `run_for` gives up.
+    budget: u64,
+    compiled: Compiled,
+}
+
+impl Native {
+    /// Starts `compiled` on `program`. `code` lists the address ranges the
+    /// generated arms embed; writing anything new there retires the arms
+    /// overlapping it.
+    pub fn new(program: &[i64], code: &[(usize, usize)], compiled: Compiled) -> Self {
+        let mut is_code = vec![false; program.len()];
+        for &(start, end) in code {
+            is_code[start..end].iter_mut().for_each(|c| *c = true);
+        }
+        let mut cpu = Cpu::new(program);
+        // Interpreted steps are observed, and observed steps never use the
+        // decode cache, so don't pay for keeping it up to date.
+        cpu.cache = DecodeCache::new(0);
+        Self {
+            cpu,
+            code: is_code.into(),
+            stale: Vec::new(),
+            budget: 0,
+            compiled,
+        }
+    }
+
+    /// The interpreter state the generated code runs on.
+    pub fn cpu(&self) -> &Cpu {
+        &self.cpu
+    }
+
+    pub fn set_overflow(&mut self, overflow: Overflow) {
+        self.cpu.set_overflow(overflow);
+    }
+
+    /// The pc to dispatch on, or `usize::MAX` if its arm is stale or the
+    /// budget ran out.
+    #[doc(hidden)]
+    #[inline]
+    pub fn fetch(&mut self) -> usize {
+        let pc = self.cpu.pc;
+        if self.budget == 0 || self.stale.get(pc) == Some(&true) {
+            return usize::MAX;
+        }
+        self.budget -= 1;
+        pc
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn goto(&mut self, pc: usize) {
+        self.cpu.pc = pc;
+    }
+
+    /// The address `addr`, or `None` if memory can't hold it.
+    #[doc(hidden)]
+    #[inline]
+    pub fn pos(&self, addr: i64) -> Option<usize> {
+        usize::try_from(addr)
+            .ok()
+            .filter(|&addr| self.cpu.mem.fits(addr))
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn rel(&self, op: i64) -> Option<usize> {
+        self.pos(self.cpu.relative_offset.checked_add(op)?)
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn arb(&mut self, value: i64) -> Option<()> {
+        self.cpu.relative_offset = self.cpu.relative_offset.checked_add(value)?;
+        Some(())
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn get(&self, addr: usize) -> i64 {
+        self.cpu.mem[addr]
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn set(&mut self, addr: usize, value: i64) {
+        if self.cpu.mem[addr] != value {
+            retire(&self.code, &mut self.stale, addr);
+        }
+        self.cpu.mem[addr] = value;
+    }
+
+    #[doc(hidden)]
+    #[inline]
+    pub fn input(&mut self) -> Option<i64> {
+        self.cpu.input.pop_front()
+    }
+}
+
+impl Machine for Native {
+    fn push_input(&mut self, value: i64) {
+        self.cpu.push_input(value);
+    }
+
+    fn run(&mut self) -> Result<Status, IntcodeError> {
+        loop {
+            if let Some(status) = self.run_for(u64::MAX)? {
+                return Ok(status);
+            }
+        }
+    }
+
+    fn run_for(&mut self, steps: u64) -> Result<Option<Status>, IntcodeError> {
+        self.budget = steps;
+        loop {
+            if let Some(status) = (self.compiled)(self) {
+                return Ok(Some(status));
+            }
+            if self.budget == 0 {
+                return Ok(None);
+            }
+            self.budget -= 1;
+            let mut watch = CodeWatch {
+                code: &self.code,
+                stale: &mut self.stale,
+            };
+            if let Some(status) = self.cpu.step_with(&mut watch)? {
+                return Ok(Some(status));
+            }
+        }
+    }
+}
+
+/// Position addresses below this fit in the `Dense` memory `Native` runs
+/// on, and are used as they are; the rest go through `Native::pos`.
+const LITERAL_LIMIT: i64 = Dense::<i64>::DEFAULT_LIMIT as i64;
+
+/// A parameter as the generated code sees it: a literal, or a read of the
+/// parameter word itself when the program patches it at runtime.
+struct Operand {
+    word: String,
+    literal: Option<i64>,
+    mode: Mode,
+}
+
+impl Operand {
+    fn new(instr: &Instruction, i: usize, patched: &BTreeSet<usize>) -> Self {
+        let addr = instr.addr + 1 + i;
+        let param = instr.params[i];
+        if patched.contains(&addr) {
+            Self {
+                word: format!("m.get({})", addr),
+                literal: None,
+                mode: param.mode,
+            }
+        } else {
+            Self {
+                word: param.value.to_string(),
+                literal: Some(param.value),
+                mode: param.mode,
+            }
+        }
+    }
+
+    /// An expression for the value the parameter resolves to.
+    fn value(&self) -> String {
+        match self.mode {
+            Mode::Immediate => self.word.clone(),
+            _ => format!("m.get({})", self.address()),
+        }
+    }
+
+    /// An expression for the address the parameter refers to.
+    fn address(&self) -> String {
+        match (self.mode, self.literal) {
+            (Mode::Position, Some(addr)) if (0..LITERAL_LIMIT).contains(&addr) => addr.to_string(),
+            (Mode::Position, _) => format!("m.pos({})?", self.word),
+            _ => format!("m.rel({})?", self.word),
+        }
+    }
+
+    /// An expression for the address a jump through the parameter goes to.
+    fn target(&self) -> String {
+        match (self.mode, self.literal) {
+            (Mode::Immediate, Some(addr)) if addr >= 0 => addr.to_string(),
+            _ => format!("m.pos({})?", self.value()),
+        }
+    }
+}
+
+fn write_arm(
+    f: &mut fmt::Formatter,
+    instr: &Instruction,
+    patched: &BTreeSet<usize>,
+) -> fmt::Result {
+    let p = |i: usize| Operand::new(instr, i, patched);
+    let next = instr.addr + instr.size();
+    writeln!(f, "            {} => {{", instr.addr)?;
+    match instr.opcode {
+        Opcode::Add | Opcode::Mul | Opcode::Lt | Opcode::Eq => {
+            let result = match instr.opcode {
+                Opcode::Add => "a.checked_add(b)?",
+                Opcode::Mul => "a.checked_mul(b)?",
+                Opcode::Lt => "(a < b) as i64",
+                _ => "(a == b) as i64",
+            };
+            writeln!(f, "                let a: i64 = {};", p(0).value())?;
+            writeln!(f, "                let b = {};", p(1).value())?;
+            writeln!(f, "                let d = {};", p(2).address())?;
+            writeln!(f, "                m.set(d, {});", result)?;
+            writeln!(f, "                m.goto({});", next)?;
+        }
+        Opcode::In => {
+            writeln!(f, "                let d = {};", p(0).address())?;
+            writeln!(f, "                let v = match m.input() {{")?;
+            writeln!(f, "                    Some(v) => v,")?;
+            writeln!(
+                f,
+                "                    None => return Some(Status::NeedsInput),"
+            )?;
+            writeln!(f, "                }};")?;
+            writeln!(f, "                m.set(d, v);")?;
+            writeln!(f, "                m.goto({});", next)?;
+        }
+        Opcode::Out => {
+            writeln!(f, "                let a = {};", p(0).value())?;
+            writeln!(f, "                m.goto({});", next)?;
+            writeln!(f, "                return Some(Status::Output(a));")?;
+        }
+        Opcode::Jnz | Opcode::Jz => {
+            let cmp = if instr.opcode == Opcode::Jnz {
+                "!="
+            } else {
+                "=="
+            };
+            writeln!(f, "                if {} {} 0 {{", p(0).value(), cmp)?;
+            writeln!(f, "                    m.goto({});", p(1).target())?;
+            writeln!(f, "                }} else {{")?;
+            writeln!(f, "                    m.goto({});", next)?;
+            writeln!(f, "                }}")?;
+        }
+        Opcode::Arb => {
+            writeln!(f, "                let a = {};", p(0).value())?;
+            writeln!(f, "                m.arb(a)?;")?;
+            writeln!(f, "                m.goto({});", next)?;
+        }
+        Opcode::Hlt => writeln!(f, "                return Some(Status::Halted);")?,
+    }
+    writeln!(f, "            }}")
+}
+
+struct Module<'a> {
+    program: &'a [i64],
+    instructions: Vec<Instruction>,
+    /// Parameter words that some instruction stores to directly.
+    patched: BTreeSet<usize>,
+}
+
+impl fmt::Display for Module<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut code: Vec<(usize, usize)> = Vec::new();
+        for instr in &self.instructions {
+            for addr in instr.addr..instr.addr + instr.size() {
+                if self.patched.contains(&addr) {
+                    continue;
+                }
+                match code.last_mut() {
+                    Some(last) if last.1 == addr => last.1 = addr + 1,
+                    _ => code.push((addr, addr + 1)),
+                }
+            }
+        }
+
+        writeln!(
+            f,
+            "// Generated by intcode::aot::transpile from a {}-word program.",
+            self.program.len()
+        )?;
+        writeln!(f)?;
+        writeln!(f, "use intcode::aot::Native;")?;
+        writeln!(f, "use intcode::Status;")?;
+        writeln!(f)?;
+        writeln!(f, "pub const PROGRAM: &[i64] = &[")?;
+        for chunk in self.program.chunks(16) {
+            let words: Vec<_> = chunk.iter().map(|w| w.to_string()).collect();
+            writeln!(f, "    {},", words.join(", "))?;
+        }
+        writeln!(f, "];")?;
+        writeln!(f)?;
+        writeln!(f, "const CODE: &[(usize, usize)] = &[")?;
+        for (start, end) in code {
+            writeln!(f, "    ({}, {}),", start, end)?;
+        }
+        writeln!(f, "];")?;
+        writeln!(f)?;
+        writeln!(f, "pub fn new() -> Native {{")?;
+        writeln!(f, "    Native::new(PROGRAM, CODE, run)")?;
+        writeln!(f, "}}")?;
+        writeln!(f)?;
+        writeln!(f, "fn run(m: &mut Native) -> Option<Status> {{")?;
+        writeln!(f, "    loop {{")?;
+        writeln!(f, "        match m.fetch() {{")?;
+        for instr in &self.instructions {
+            write_arm(f, instr, &self.patched)?;
+        }
+        writeln!(f, "            _ => return None,")?;
+        writeln!(f, "        }}")?;
+        writeln!(f, "    }}")?;
+        writeln!(f, "}}")
+    }
+}
+
+/// Translates `program` into Rust source for a module exposing `PROGRAM`,
+/// the words it was generated from, and `new() -> Native`.
+///
+/// Besides what is reachable from pc 0, any data word holding the address
+/// of another data word that decodes is tried as an entry point, which
+/// picks up jump tables. Parameter words the program stores to through a
+/// constant address, as code that patches its own jump targets does, are
+/// read from memory at runtime; any other write into the code retires the
+/// arms it overlaps. Every `?` in the generated code stands for a fault:
+/// it returns before the instruction changes any state, and the
+/// interpreter reports it.
+pub fn transpile(program: &[i64]) -> String {
+    let reachable = disassemble(program);
+    let data: Vec<_> = reachable.data_ranges();
+    let in_data = |addr: usize| {
+        data.iter()
+            .any(|&(start, end)| (start..end).contains(&addr))
+    };
+    let mut roots = vec![0];
+    for &(start, end) in &data {
+        roots.extend(
+            program[start..end]
+                .iter()
+                .filter_map(|&word| usize::try_from(word).ok())
+                .filter(|&addr| in_data(addr) && Instruction::decode(program, addr).is_some()),
+        );
+    }
+
+    let instructions: Vec<_> = disassemble_from(program, &roots)
+        .instructions
+        .into_values()
+        .collect();
+    let params: BTreeSet<usize> = instructions
+        .iter()
+        .flat_map(|instr| instr.addr + 1..instr.addr + instr.size())
+        .collect();
+    let patched = instructions
+        .iter()
+        .filter_map(|instr| {
+            let param = instr.params[instr.opcode.write_param()?];
+            match param.mode {
+                Mode::Position => usize::try_from(param.value).ok(),
+                _ => None,
+            }
+        })
+        .filter(|addr| params.contains(addr))
+        .collect();
+    Module {
+        program,
+        instructions,
+        patched,
+    }
+    .to_string()
+}
@@ -0,0 +1,97 @@
+//! Talking to programs that speak ASCII: send a line of text, read what
+//! comes back up to a prompt or until the program wants input.
+
+use crate::cpu::{Cpu, Machine, Status};
+use crate::error::IntcodeError;
+use std::convert::TryFrom;
+
+/// What a program wrote between two reads.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Reply {
+    /// The ASCII output.
+    pub text: String,
+    /// Output outside ASCII, such as a puzzle's answer, in order.
+    pub values: Vec<i64>,
+}
+
+impl Reply {
+    fn push(&mut self, value: i64) {
+        match u8::try_from(value) {
+            Ok(c) if c.is_ascii() => self.text.push(char::from(c)),
+            _ => self.values.push(value),
+        }
+    }
+}
+
+/// A machine driven as a text session.
+pub struct Session<M = Cpu> {
+    machine: M,
+    halted: bool,
+}
+
+impl<M: Machine> Session<M> {
+    pub fn new(machine: M) -> Self {
+        Self {
+            machine,
+            halted: false,
+        }
+    }
+
+    pub fn machine(&self) -> &M {
+        &self.machine
+    }
+
+    pub fn machine_mut(&mut self) -> &mut M {
+        &mut self.machine
+    }
+
+    pub fn into_inner(self) -> M {
+        self.machine
+    }
+
+    /// Whether the program has halted, so nothing more will be read.
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Queues `line` as input, followed by a newline.
+    pub fn send_line(&mut self, line: &str) {
+        for b in line.bytes().chain(Some(b'\n')) {
+            self.machine.push_input(i64::from(b));
+        }
+    }
+
+    /// Reads until the program halts or wants input it hasn't been sent.
+    pub fn read_all(&mut self) -> Result<Reply, IntcodeError> {
+        self.read(|_| false)
+    }
+
+    /// Reads until the text ends with `prompt`, or as `read_all` if it
+    /// never does.
+    pub fn read_until(&mut self, prompt: &str) -> Result<Reply, IntcodeError> {
+        self.read(|reply| reply.text.ends_with(prompt))
+    }
+
+    /// Sends `line` and reads the whole answer to it.
+    pub fn command(&mut self, line: &str) -> Result<Reply, IntcodeError> {
+        self.send_line(line);
+        self.read_all()
+    }
+
+    fn read(&mut self, mut done: impl FnMut(&Reply) -> bool) -> Result<Reply, IntcodeError> {
+        let mut reply = Reply::default();
+        while !self.halted {
+            match self.machine.run()? {
+                Status::Output(value) => {
+                    reply.push(value);
+                    if done(&reply) {
+                        break;
+                    }
+                }
+                Status::NeedsInput => break,
+                Status::Halted => self.halted = true,
+            }
+        }
+        Ok(reply)
+    }
+}
@@ -0,0 +1,390 @@
+use crate::opcode::{Mode, Opcode};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AsmErrorKind {
+    UnknownMnemonic(String),
+    UnknownDirective(String),
+    OperandCount { expected: usize, found: usize },
+    InvalidOperand(String),
+    ImmediateDestination,
+    DuplicateLabel(String),
+    UndefinedLabel(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub kind: AsmErrorKind,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            AsmErrorKind::UnknownMnemonic(m) => write!(f, "unknown mnemonic: {}", m),
+            AsmErrorKind::UnknownDirective(d) => write!(f, "unknown directive: {}", d),
+            AsmErrorKind::OperandCount { expected, found } => {
+                write!(f, "expected {} operands, found {}", expected, found)
+            }
+            AsmErrorKind::InvalidOperand(op) => write!(f, "invalid operand: {}", op),
+            AsmErrorKind::ImmediateDestination => {
+                write!(f, "destination operand cannot be immediate")
+            }
+            AsmErrorKind::DuplicateLabel(l) => write!(f, "duplicate label: {}", l),
+            AsmErrorKind::UndefinedLabel(l) => write!(f, "undefined label: {}", l),
+        }
+    }
+}
+
+impl Error for AsmError {}
+
+#[derive(Debug)]
+enum Term {
+    Number(i64),
+    Label(String),
+}
+
+/// A sum of signed terms, resolved once every label address is known.
+#[derive(Debug)]
+struct Expr {
+    terms: Vec<(i64, Term)>,
+}
+
+impl Expr {
+    fn resolve(&self, labels: &HashMap<String, i64>) -> Result<i64, AsmErrorKind> {
+        let mut value = 0i64;
+        for (sign, term) in &self.terms {
+            let v = match term {
+                Term::Number(n) => *n,
+                Term::Label(l) => *labels
+                    .get(l)
+                    .ok_or_else(|| AsmErrorKind::UndefinedLabel(l.clone()))?,
+            };
+            value = value.wrapping_add(sign.wrapping_mul(v));
+        }
+        Ok(value)
+    }
+}
+
+#[derive(Debug)]
+struct Operand {
+    mode: Mode,
+    expr: Expr,
+}
+
+#[derive(Debug)]
+enum Item {
+    Instruction(Opcode, Vec<Operand>),
+    Data(Vec<Expr>),
+}
+
+impl Item {
+    fn len(&self) -> usize {
+        match self {
+            Item::Instruction(opcode, _) => opcode.size(),
+            Item::Data(values) => values.len(),
+        }
+    }
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || c == '.'
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.'
+}
+
+fn unescape(c: char) -> Option<char> {
+    Some(match c {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        '0' => '\0',
+        '\\' | '\'' | '"' => c,
+        _ => return None,
+    })
+}
+
+/// Removes a `;` comment, ignoring semicolons inside quotes.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match quote {
+            Some(q) => {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+            }
+            None => match c {
+                '\'' | '"' => quote = Some(c),
+                ';' => return &line[..i],
+                _ => {}
+            },
+        }
+    }
+    line
+}
+
+/// Splits on top-level commas, keeping quoted strings intact.
+fn split_operands(s: &str) -> Vec<&str> {
+    let mut operands = Vec::new();
+    let mut quote = None;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match quote {
+            Some(q) => {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+            }
+            None => match c {
+                '\'' | '"' => quote = Some(c),
+                ',' => {
+                    operands.push(s[start..i].trim());
+                    start = i + 1;
+                }
+                _ => {}
+            },
+        }
+    }
+    let last = s[start..].trim();
+    if !last.is_empty() || !operands.is_empty() {
+        operands.push(last);
+    }
+    operands
+}
+
+fn parse_char(s: &str) -> Option<i64> {
+    let inner = s.strip_prefix('\'')?.strip_suffix('\'')?;
+    let mut chars = inner.chars();
+    let c = match chars.next()? {
+        '\\' => unescape(chars.next()?)?,
+        c => c,
+    };
+    if chars.next().is_some() {
+        return None;
+    }
+    Some(c as i64)
+}
+
+fn parse_term(s: &str) -> Option<Term> {
+    if s.starts_with('\'') {
+        parse_char(s).map(Term::Number)
+    } else if let Some(hex) = s.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok().map(Term::Number)
+    } else if s.starts_with(|c: char| c.is_ascii_digit()) {
+        s.parse().ok().map(Term::Number)
+    } else if s.starts_with(is_ident_start) && s.chars().all(is_ident) {
+        Some(Term::Label(s.to_string()))
+    } else {
+        None
+    }
+}
+
+fn parse_expr(s: &str) -> Option<Expr> {
+    let mut terms = Vec::new();
+    let mut sign = 1;
+    let mut rest = s.trim();
+    if rest.is_empty() {
+        return None;
+    }
+    loop {
+        if let Some(r) = rest.strip_prefix('-') {
+            sign = -sign;
+            rest = r.trim_start();
+            continue;
+        }
+        if let Some(r) = rest.strip_prefix('+') {
+            rest = r.trim_start();
+            continue;
+        }
+        let end = if rest.starts_with('\'') {
+            let mut escaped = false;
+            rest.char_indices()
+                .skip(1)
+                .find(|&(_, c)| {
+                    let end = !escaped && c == '\'';
+                    escaped = !escaped && c == '\\';
+                    end
+                })
+                .map(|(i, _)| i + 1)?
+        } else {
+            rest.find(|c: char| c == '+' || c == '-' || c.is_whitespace())
+                .unwrap_or(rest.len())
+        };
+        terms.push((sign, parse_term(&rest[..end])?));
+        rest = rest[end..].trim_start();
+        if rest.is_empty() {
+            return Some(Expr { terms });
+        }
+        sign = match rest.as_bytes()[0] {
+            b'+' => 1,
+            b'-' => -1,
+            _ => return None,
+        };
+        rest = rest[1..].trim_start();
+    }
+}
+
+fn parse_operand(s: &str) -> Result<Operand, AsmErrorKind> {
+    let invalid = || AsmErrorKind::InvalidOperand(s.to_string());
+    let operand = if let Some(imm) = s.strip_prefix('#') {
+        Operand {
+            mode: Mode::Immediate,
+            expr: parse_expr(imm).ok_or_else(invalid)?,
+        }
+    } else if let Some(addr) = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        Operand {
+            mode: Mode::Position,
+            expr: parse_expr(addr).ok_or_else(invalid)?,
+        }
+    } else if s.starts_with("rb") && !s[2..].starts_with(is_ident) {
+        let offset = s[2..].trim();
+        Operand {
+            mode: Mode::Relative,
+            expr: if offset.is_empty() {
+                Expr {
+                    terms: vec![(1, Term::Number(0))],
+                }
+            } else if offset.starts_with('+') || offset.starts_with('-') {
+                parse_expr(offset).ok_or_else(invalid)?
+            } else {
+                return Err(invalid());
+            },
+        }
+    } else {
+        return Err(invalid());
+    };
+    Ok(operand)
+}
+
+fn parse_data(s: &str) -> Result<Vec<Expr>, AsmErrorKind> {
+    let mut values = Vec::new();
+    for item in split_operands(s) {
+        let invalid = || AsmErrorKind::InvalidOperand(item.to_string());
+        if let Some(inner) = item.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+            let mut chars = inner.chars();
+            while let Some(c) = chars.next() {
+                let c = match c {
+                    '\\' => chars.next().and_then(unescape).ok_or_else(invalid)?,
+                    c => c,
+                };
+                values.push(Expr {
+                    terms: vec![(1, Term::Number(c as i64))],
+                });
+            }
+        } else {
+            values.push(parse_expr(item).ok_or_else(invalid)?);
+        }
+    }
+    Ok(values)
+}
+
+fn parse_item(s: &str) -> Result<Item, AsmErrorKind> {
+    let (head, rest) = match s.find(char::is_whitespace) {
+        Some(i) => (&s[..i], s[i..].trim()),
+        None => (s, ""),
+    };
+
+    if head.starts_with('.') {
+        return match head {
+            ".data" => Ok(Item::Data(parse_data(rest)?)),
+            _ => Err(AsmErrorKind::UnknownDirective(head.to_string())),
+        };
+    }
+
+    let opcode = Opcode::from_mnemonic(&head.to_lowercase())
+        .ok_or_else(|| AsmErrorKind::UnknownMnemonic(head.to_string()))?;
+    let operands = split_operands(rest)
+        .into_iter()
+        .map(parse_operand)
+        .collect::<Result<Vec<_>, _>>()?;
+    if operands.len() != opcode.params() {
+        return Err(AsmErrorKind::OperandCount {
+            expected: opcode.params(),
+            found: operands.len(),
+        });
+    }
+    if let Some(dst) = opcode.write_param() {
+        if operands[dst].mode == Mode::Immediate {
+            return Err(AsmErrorKind::ImmediateDestination);
+        }
+    }
+    Ok(Item::Instruction(opcode, operands))
+}
+
+/// Assembles Intcode source into a program image.
+///
+/// Each line holds an optional `label:`, then either an instruction such as
+/// `add [x], #1, rb-2` or a `.data` directive listing numbers, labels,
+/// `'c'` characters and `"strings"`. Comments start with `;`. Operand
+/// values are sums of numbers and labels, e.g. `[buffer+2]`.
+pub fn assemble(source: &str) -> Result<Vec<i64>, AsmError> {
+    let mut labels = HashMap::new();
+    let mut items = Vec::new();
+    let mut addr = 0;
+
+    for (i, line) in source.lines().enumerate() {
+        let error = |kind| AsmError { line: i + 1, kind };
+        let mut line = strip_comment(line).trim();
+
+        while let Some(colon) = line.find(':') {
+            let label = line[..colon].trim();
+            if !label.starts_with(is_ident_start) || !label.chars().all(is_ident) {
+                break;
+            }
+            if labels.insert(label.to_string(), addr as i64).is_some() {
+                return Err(error(AsmErrorKind::DuplicateLabel(label.to_string())));
+            }
+            line = line[colon + 1..].trim();
+        }
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let item = parse_item(line).map_err(error)?;
+        addr += item.len();
+        items.push((i + 1, item));
+    }
+
+    let mut program = Vec::with_capacity(addr);
+    for (line, item) in items {
+        let error = |kind| AsmError { line, kind };
+        match item {
+            Item::Instruction(opcode, operands) => {
+                let mut instr = opcode.code();
+                let mut scale = 100;
+                for operand in &operands {
+                    instr += operand.mode.code() * scale;
+                    scale *= 10;
+                }
+                program.push(instr);
+                for operand in operands {
+                    program.push(operand.expr.resolve(&labels).map_err(error)?);
+                }
+            }
+            Item::Data(values) => {
+                for value in values {
+                    program.push(value.resolve(&labels).map_err(error)?);
+                }
+            }
+        }
+    }
+
+    Ok(program)
+}
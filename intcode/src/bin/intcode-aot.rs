@@ -0,0 +1,23 @@
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::process;
+
+fn main() {
+    let input = match env::args().nth(1) {
+        Some(path) => fs::read_to_string(path).unwrap(),
+        None => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input).unwrap();
+            input
+        }
+    };
+
+    match intcode::parse_program(&input) {
+        Ok(program) => print!("{}", intcode::aot::transpile(&program)),
+        Err(e) => {
+            eprintln!("invalid program: {}", e);
+            process::exit(1);
+        }
+    }
+}
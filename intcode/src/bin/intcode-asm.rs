@@ -0,0 +1,30 @@
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::process;
+
+fn main() {
+    let source = match env::args().nth(1) {
+        Some(path) => fs::read_to_string(path).unwrap(),
+        None => {
+            let mut source = String::new();
+            io::stdin().read_to_string(&mut source).unwrap();
+            source
+        }
+    };
+
+    match intcode::asm::assemble(&source) {
+        Ok(program) => println!(
+            "{}",
+            program
+                .iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        ),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
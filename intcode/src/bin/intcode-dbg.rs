@@ -0,0 +1,42 @@
+use intcode::debugger::Debugger;
+use intcode::Cpu;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::process;
+
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: intcode-dbg <program>");
+            process::exit(1);
+        }
+    };
+    let program = match intcode::parse_program(&fs::read_to_string(path).unwrap()) {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("invalid program: {}", e);
+            process::exit(1);
+        }
+    };
+
+    let mut dbg = Debugger::new(Cpu::new(&program));
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    dbg.command("dis", &mut out).unwrap();
+    loop {
+        write!(out, "(dbg) ").unwrap();
+        out.flush().unwrap();
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap() == 0 {
+            break;
+        }
+        if !dbg.command(&line, &mut out).unwrap() {
+            break;
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use intcode::fuzz::{self, Program};
+use std::env;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const USAGE: &str = "usage: intcode-fuzz [--seed N] [--runs N] [--show]";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(1);
+}
+
+fn main() {
+    let mut seed = None;
+    let mut runs = 10_000;
+    let mut show = false;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--seed" => seed = Some(value().parse().unwrap_or_else(|_| usage())),
+            "--runs" => runs = value().parse().unwrap_or_else(|_| usage()),
+            "--show" => show = true,
+            _ => usage(),
+        }
+    }
+
+    let seed = seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs())
+    });
+    eprintln!("seeds {}..{}", seed, seed.wrapping_add(runs));
+
+    for seed in (0..runs).map(|run| seed.wrapping_add(run)) {
+        let program = Program::generate(seed);
+        if show {
+            println!("; seed {}\n{}", seed, program.source());
+        }
+        let divergence = match fuzz::check(&program.image(), &program.input) {
+            Ok(()) => continue,
+            Err(divergence) => divergence,
+        };
+
+        println!("seed {}: {}", seed, divergence);
+        let minimized = fuzz::minimize(&program, |program| {
+            fuzz::check(&program.image(), &program.input).is_err()
+        });
+        let divergence = fuzz::check(&minimized.image(), &minimized.input).unwrap_err();
+        println!("minimized: {}\n", divergence);
+        print!("{}", minimized.source());
+        process::exit(1);
+    }
+    eprintln!("no divergence in {} programs", runs);
+}
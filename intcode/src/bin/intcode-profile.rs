@@ -0,0 +1,99 @@
+use intcode::profile::Profiler;
+use intcode::{Cpu, Status};
+use std::convert::TryFrom;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufWriter, Write};
+use std::process;
+
+const USAGE: &str = "usage: intcode-profile [--input N,...] [--text LINE]... [--top N] \
+[--folded FILE] <program>";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(1);
+}
+
+fn main() {
+    let mut input = Vec::new();
+    let mut top = 20;
+    let mut folded = None;
+    let mut path = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--input" => {
+                for n in value().split(',') {
+                    input.push(n.trim().parse().unwrap_or_else(|_| usage()));
+                }
+            }
+            "--text" => input.extend(value().bytes().chain(Some(b'\n')).map(|b| b as i64)),
+            "--top" => top = value().parse().unwrap_or_else(|_| usage()),
+            "--folded" => folded = Some(value()),
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg),
+            _ => usage(),
+        }
+    }
+
+    let path = path.unwrap_or_else(|| usage());
+    let program = match intcode::parse_program(&fs::read_to_string(path).unwrap()) {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("invalid program: {}", e);
+            process::exit(1);
+        }
+    };
+
+    let mut cpu = Cpu::new(&program);
+    input.into_iter().for_each(|value| cpu.push_input(value));
+
+    // Once the queued input runs out, lines from stdin are fed to the
+    // program as ASCII, so interactive programs can be profiled too.
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut profiler = Profiler::new();
+    loop {
+        match cpu.run_with(&mut profiler) {
+            Ok(Status::Output(value)) => match u8::try_from(value) {
+                Ok(c) if c.is_ascii() => eprint!("{}", c as char),
+                _ => eprintln!("output: {}", value),
+            },
+            Ok(Status::NeedsInput) => match lines.next() {
+                Some(Ok(line)) => {
+                    for b in line.bytes().chain(Some(b'\n')) {
+                        cpu.push_input(b as i64);
+                    }
+                }
+                _ => {
+                    eprintln!("waiting for input at pc {}", cpu.pc());
+                    break;
+                }
+            },
+            Ok(Status::Halted) => break,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                break;
+            }
+        }
+    }
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    if let Err(e) = profiler.report(&mut out, top).and_then(|()| out.flush()) {
+        eprintln!("error writing report: {}", e);
+        process::exit(1);
+    }
+    if let Some(folded) = folded {
+        let written = File::create(&folded).and_then(|file| {
+            let mut file = BufWriter::new(file);
+            profiler.folded(&mut file)?;
+            file.flush()
+        });
+        if let Err(e) = written {
+            eprintln!("error writing {}: {}", folded, e);
+            process::exit(1);
+        }
+    }
+}
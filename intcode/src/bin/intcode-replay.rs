@@ -0,0 +1,51 @@
+use intcode::record::{self, Log};
+use intcode::Cpu;
+use std::env;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::process;
+
+const USAGE: &str = "usage: intcode-replay <program|snapshot> <log>";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(1);
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn main() {
+    let args: Vec<_> = env::args().skip(1).collect();
+    let (path, log_path) = match args.as_slice() {
+        [path, log] => (path, log),
+        _ => usage(),
+    };
+
+    // A session that started from a saved state replays from the snapshot.
+    let source = fs::read_to_string(path).unwrap_or_else(|e| fail(format!("{}: {}", path, e)));
+    let cpu = if source.starts_with("intcode-snapshot") {
+        Cpu::restore(source.as_bytes()).unwrap_or_else(|e| fail(format!("{}: {}", path, e)))
+    } else {
+        match intcode::parse_program(&source) {
+            Ok(program) => Cpu::new(&program),
+            Err(e) => fail(format!("invalid program: {}", e)),
+        }
+    };
+
+    let log = File::open(log_path)
+        .map_err(Into::into)
+        .and_then(|file| Log::load(BufReader::new(file)))
+        .unwrap_or_else(|e| fail(format!("{}: {}", log_path, e)));
+
+    match record::replay(cpu, &log) {
+        Ok(()) => println!(
+            "replayed {} entries over {} instructions",
+            log.entries.len(),
+            log.steps
+        ),
+        Err(e) => fail(format!("replay diverged: {}", e)),
+    }
+}
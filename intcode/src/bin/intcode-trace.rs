@@ -0,0 +1,98 @@
+use intcode::trace::{TraceFilter, Tracer};
+use intcode::{Cpu, Opcode, Status};
+use std::env;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::process;
+
+const USAGE: &str = "usage: intcode-trace [--pc A..B] [--opcode OP,...] [--steps A..B] \
+[--input N,...] [--text LINE]... [--code-writes] <program>";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(1);
+}
+
+fn parse_range<T: std::str::FromStr>(s: &str) -> Range<T> {
+    let mut bounds = s.splitn(2, "..");
+    match (
+        bounds.next().and_then(|b| b.parse().ok()),
+        bounds.next().and_then(|b| b.parse().ok()),
+    ) {
+        (Some(start), Some(end)) => start..end,
+        _ => usage(),
+    }
+}
+
+fn parse_opcode(s: &str) -> Opcode {
+    Opcode::from_mnemonic(s)
+        .or_else(|| s.parse().ok().and_then(Opcode::from_code))
+        .unwrap_or_else(|| usage())
+}
+
+fn main() {
+    let mut filter = TraceFilter::default();
+    let mut input = Vec::new();
+    let mut code_writes = false;
+    let mut path = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--pc" => filter.pc = Some(parse_range(&value())),
+            "--steps" => filter.steps = Some(parse_range(&value())),
+            "--opcode" => filter.opcodes = Some(value().split(',').map(parse_opcode).collect()),
+            "--input" => {
+                for n in value().split(',') {
+                    input.push(n.trim().parse().unwrap_or_else(|_| usage()));
+                }
+            }
+            "--text" => input.extend(value().bytes().chain(Some(b'\n')).map(|b| b as i64)),
+            "--code-writes" => code_writes = true,
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg),
+            _ => usage(),
+        }
+    }
+
+    let path = path.unwrap_or_else(|| usage());
+    let program = match intcode::parse_program(&fs::read_to_string(path).unwrap()) {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("invalid program: {}", e);
+            process::exit(1);
+        }
+    };
+
+    let mut cpu = Cpu::new(&program);
+    cpu.detect_code_writes(code_writes);
+    input.into_iter().for_each(|value| cpu.push_input(value));
+
+    let stdout = io::stdout();
+    let mut tracer = Tracer::new(BufWriter::new(stdout.lock()), filter);
+    loop {
+        let status = cpu.run_with(&mut tracer);
+        for write in cpu.take_code_writes() {
+            eprintln!("{}", write);
+        }
+        match status {
+            Ok(Status::Output(value)) => eprintln!("output: {}", value),
+            Ok(Status::NeedsInput) => {
+                eprintln!("waiting for input at pc {}", cpu.pc());
+                break;
+            }
+            Ok(Status::Halted) => break,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                break;
+            }
+        }
+    }
+    eprintln!("{} instructions executed", tracer.steps());
+
+    if let Err(e) = tracer.finish().and_then(|mut out| out.flush()) {
+        eprintln!("error writing trace: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,100 @@
+use crate::cell::Cell;
+use crate::memory::Memory;
+use crate::opcode::{decode, Mode, Opcode};
+use std::sync::{Arc, OnceLock};
+
+/// A parameter with its mode already resolved.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Operand<C> {
+    Immediate(C),
+    Position(usize),
+    Relative(C),
+}
+
+/// An instruction decoded once, for an interpreter that doesn't redo the
+/// divisions, mode checks and parameter fetches on every visit.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Entry<C> {
+    pub(crate) opcode: Opcode,
+    pub(crate) params: [Operand<C>; 3],
+}
+
+impl<C: Cell> Entry<C> {
+    /// Decodes the instruction at `pc` if it lies entirely below `len` and
+    /// can't fault on its opcode, its modes or a position address. Anything
+    /// else is left to the plain interpreter, which reports the error.
+    fn decode<M: Memory<Cell = C>>(mem: &M, pc: usize, len: usize) -> Option<Self> {
+        let (code, modes) = decode(mem[pc].to_i64()?);
+        let opcode = Opcode::from_code(code)?;
+        if pc + opcode.size() > len {
+            return None;
+        }
+        let mut params = [
+            Operand::Position(0),
+            Operand::Position(0),
+            Operand::Position(0),
+        ];
+        for (i, param) in params.iter_mut().enumerate().take(opcode.params()) {
+            let value = mem[pc + 1 + i].clone();
+            *param = match Mode::from_code(modes[i])? {
+                Mode::Immediate if opcode.write_param() == Some(i) => return None,
+                Mode::Immediate => Operand::Immediate(value),
+                Mode::Position => Operand::Position(value.to_usize()?),
+                Mode::Relative => Operand::Relative(value),
+            };
+        }
+        Some(Self { opcode, params })
+    }
+}
+
+/// Decoded instructions for the first `len` cells of memory as they were
+/// when the cache was created, filled in lazily and shared between clones
+/// of a CPU. Each CPU tracks which of those cells it has since written to,
+/// and never uses an entry that might overlap one of them.
+#[derive(Clone)]
+pub(crate) struct DecodeCache<C = i64> {
+    image: Arc<[OnceLock<Option<Entry<C>>>]>,
+    dirty: Vec<u64>,
+}
+
+impl<C: Cell> DecodeCache<C> {
+    pub(crate) fn new(len: usize) -> Self {
+        Self {
+            image: (0..len).map(|_| OnceLock::new()).collect(),
+            dirty: Vec::new(),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.image.len()
+    }
+
+    fn is_dirty(&self, addr: usize) -> bool {
+        self.dirty
+            .get(addr / 64)
+            .is_some_and(|&word| word & (1 << (addr % 64)) != 0)
+    }
+
+    pub(crate) fn get<M: Memory<Cell = C>>(&self, mem: &M, pc: usize) -> Option<Entry<C>> {
+        let slot = self.image.get(pc)?;
+        if self.is_dirty(pc) {
+            return None;
+        }
+        slot.get_or_init(|| Entry::decode(mem, pc, self.image.len()))
+            .clone()
+    }
+
+    /// Records a write to `addr`, retiring every entry whose instruction
+    /// could cover it.
+    pub(crate) fn invalidate(&mut self, addr: usize) {
+        if addr >= self.image.len() {
+            return;
+        }
+        if self.dirty.is_empty() {
+            self.dirty = vec![0; self.image.len() / 64 + 1];
+        }
+        for a in addr.saturating_sub(3)..=addr {
+            self.dirty[a / 64] |= 1 << (a % 64);
+        }
+    }
+}
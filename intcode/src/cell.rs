@@ -0,0 +1,134 @@
+use num_bigint::{BigInt, Sign};
+use std::convert::TryFrom;
+use std::fmt;
+use std::hash::Hash;
+use std::str::FromStr;
+
+/// A memory cell's integer type. `i64` is what the puzzles need; narrower
+/// types overflow sooner, `BigInt` never does, and `usize` can't hold a
+/// negative value at all.
+pub trait Cell:
+    Clone + Default + Ord + Hash + fmt::Debug + fmt::Display + FromStr + 'static
+{
+    fn from_i64(value: i64) -> Option<Self>;
+
+    fn to_i64(&self) -> Option<i64>;
+
+    fn to_usize(&self) -> Option<usize>;
+
+    fn checked_add(&self, rhs: &Self) -> Option<Self>;
+    fn checked_mul(&self, rhs: &Self) -> Option<Self>;
+    fn wrapping_add(&self, rhs: &Self) -> Self;
+    fn wrapping_mul(&self, rhs: &Self) -> Self;
+    fn saturating_add(&self, rhs: &Self) -> Self;
+    fn saturating_mul(&self, rhs: &Self) -> Self;
+
+    fn is_zero(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The value as an `i64`, clamped to its range. Errors report cells
+    /// this way.
+    fn clamped(&self) -> i64 {
+        self.to_i64().unwrap_or(if *self < Self::default() {
+            i64::MIN
+        } else {
+            i64::MAX
+        })
+    }
+}
+
+macro_rules! primitive_cell {
+    ($($t:ty),*) => {$(
+        impl Cell for $t {
+            #[inline]
+            fn from_i64(value: i64) -> Option<Self> {
+                <$t>::try_from(value).ok()
+            }
+
+            #[inline]
+            fn to_i64(&self) -> Option<i64> {
+                i64::try_from(*self).ok()
+            }
+
+            #[inline]
+            fn to_usize(&self) -> Option<usize> {
+                usize::try_from(*self).ok()
+            }
+
+            #[inline]
+            fn checked_add(&self, rhs: &Self) -> Option<Self> {
+                <$t>::checked_add(*self, *rhs)
+            }
+
+            #[inline]
+            fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+                <$t>::checked_mul(*self, *rhs)
+            }
+
+            #[inline]
+            fn wrapping_add(&self, rhs: &Self) -> Self {
+                <$t>::wrapping_add(*self, *rhs)
+            }
+
+            #[inline]
+            fn wrapping_mul(&self, rhs: &Self) -> Self {
+                <$t>::wrapping_mul(*self, *rhs)
+            }
+
+            #[inline]
+            fn saturating_add(&self, rhs: &Self) -> Self {
+                <$t>::saturating_add(*self, *rhs)
+            }
+
+            #[inline]
+            fn saturating_mul(&self, rhs: &Self) -> Self {
+                <$t>::saturating_mul(*self, *rhs)
+            }
+        }
+    )*};
+}
+
+primitive_cell!(i32, i64, i128, usize);
+
+impl Cell for BigInt {
+    fn from_i64(value: i64) -> Option<Self> {
+        Some(value.into())
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        i64::try_from(self).ok()
+    }
+
+    fn to_usize(&self) -> Option<usize> {
+        usize::try_from(self).ok()
+    }
+
+    fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        Some(self + rhs)
+    }
+
+    fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        Some(self * rhs)
+    }
+
+    fn wrapping_add(&self, rhs: &Self) -> Self {
+        self + rhs
+    }
+
+    fn wrapping_mul(&self, rhs: &Self) -> Self {
+        self * rhs
+    }
+
+    fn saturating_add(&self, rhs: &Self) -> Self {
+        self + rhs
+    }
+
+    fn saturating_mul(&self, rhs: &Self) -> Self {
+        self * rhs
+    }
+
+    fn is_zero(&self) -> bool {
+        self.sign() == Sign::NoSign
+    }
+}
@@ -0,0 +1,201 @@
+//! Splits a disassembled program into basic blocks connected by its jumps,
+//! and exports the graph in Graphviz DOT.
+
+use crate::disasm::{self, call_return, Disassembly, Flow, Instruction};
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    /// The block starting at this address.
+    Block(usize),
+    /// An address only known at runtime, or one that isn't the start of a
+    /// decoded instruction.
+    Unknown,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// Straight on to the next instruction.
+    Next,
+    /// A conditional jump that's taken.
+    Taken,
+    /// A conditional jump that isn't.
+    NotTaken,
+    /// An unconditional jump.
+    Jump,
+    /// Where a call returns to once the callee jumps back.
+    Return,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Edge {
+    pub kind: EdgeKind,
+    pub target: Target,
+}
+
+/// A run of instructions only entered at the first and only left after
+/// the last.
+#[derive(Clone, Debug)]
+pub struct Block {
+    pub start: usize,
+    pub instructions: Vec<Instruction>,
+    pub edges: Vec<Edge>,
+}
+
+impl Block {
+    /// The address right after the block's last instruction.
+    pub fn end(&self) -> usize {
+        self.instructions
+            .last()
+            .map_or(self.start, |instr| instr.addr + instr.size())
+    }
+
+    pub fn last(&self) -> &Instruction {
+        self.instructions.last().expect("blocks aren't empty")
+    }
+
+    /// The return address, if the block ends in a call.
+    pub fn call_return(&self, program: &[i64]) -> Option<usize> {
+        let call = self.instructions.iter().rev().nth(1)?;
+        call_return(program, call).filter(|&ret| ret == self.end())
+    }
+}
+
+pub struct Cfg {
+    pub disassembly: Disassembly,
+    pub blocks: BTreeMap<usize, Block>,
+}
+
+/// Disassembles `program` from pc 0 and builds its graph.
+pub fn build(program: &[i64]) -> Cfg {
+    Cfg::new(disasm::disassemble(program))
+}
+
+impl Cfg {
+    pub fn new(disassembly: Disassembly) -> Self {
+        let mut blocks = BTreeMap::new();
+        let mut current: Option<Block> = None;
+        for (&addr, instr) in &disassembly.instructions {
+            let continues = current.as_ref().is_some_and(|block| {
+                block.end() == addr
+                    && block.last().flow() == Flow::Next
+                    && !disassembly.targets.contains(&addr)
+            });
+            if !continues {
+                if let Some(block) = current.take() {
+                    blocks.insert(block.start, block);
+                }
+            }
+            current
+                .get_or_insert_with(|| Block {
+                    start: addr,
+                    instructions: Vec::new(),
+                    edges: Vec::new(),
+                })
+                .instructions
+                .push(instr.clone());
+        }
+        blocks.extend(current.map(|block| (block.start, block)));
+
+        let starts: Vec<usize> = blocks.keys().copied().collect();
+        let target = |addr: usize| match starts.binary_search(&addr) {
+            Ok(_) => Target::Block(addr),
+            Err(_) => Target::Unknown,
+        };
+        for block in blocks.values_mut() {
+            let next = block.end();
+            let edge = |kind, target| Edge { kind, target };
+            let mut edges = match block.last().flow() {
+                // Running into an instruction that wasn't decoded faults.
+                Flow::Next if starts.binary_search(&next).is_ok() => {
+                    vec![edge(EdgeKind::Next, Target::Block(next))]
+                }
+                Flow::Next | Flow::Halt => vec![],
+                Flow::Jump(addr) => vec![edge(EdgeKind::Jump, target(addr))],
+                Flow::Indirect => vec![edge(EdgeKind::Jump, Target::Unknown)],
+                Flow::Branch(addr) => vec![
+                    edge(EdgeKind::Taken, target(addr)),
+                    edge(EdgeKind::NotTaken, target(next)),
+                ],
+                Flow::IndirectBranch => vec![
+                    edge(EdgeKind::Taken, Target::Unknown),
+                    edge(EdgeKind::NotTaken, target(next)),
+                ],
+            };
+            if let Some(ret) = block.call_return(&disassembly.program) {
+                edges.push(edge(EdgeKind::Return, target(ret)));
+            }
+            block.edges = edges;
+        }
+
+        Self {
+            disassembly,
+            blocks,
+        }
+    }
+
+    /// The blocks with an edge to the one at `start`.
+    pub fn predecessors(&self, start: usize) -> Vec<usize> {
+        self.blocks
+            .values()
+            .filter(|block| {
+                block
+                    .edges
+                    .iter()
+                    .any(|edge| edge.target == Target::Block(start))
+            })
+            .map(|block| block.start)
+            .collect()
+    }
+
+    /// The graph in Graphviz DOT, with each block's disassembly in its
+    /// node.
+    pub fn dot(&self) -> Dot<'_> {
+        Dot(self)
+    }
+}
+
+pub struct Dot<'a>(&'a Cfg);
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl fmt::Display for Dot<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cfg = self.0;
+        writeln!(f, "digraph cfg {{")?;
+        writeln!(f, "    node [shape=box, fontname=\"monospace\"];")?;
+        for block in cfg.blocks.values() {
+            // Left-aligned lines, the heading named like the listing's label.
+            let mut label = format!("l{}:\\l", block.start);
+            for instr in &block.instructions {
+                let text = cfg.disassembly.instruction_text(instr);
+                label += &format!("{:<6}{}\\l", instr.addr, escape(&text));
+            }
+            writeln!(f, "    b{} [label=\"{}\"];", block.start, label)?;
+        }
+
+        for block in cfg.blocks.values() {
+            for (i, edge) in block.edges.iter().enumerate() {
+                let to = match edge.target {
+                    Target::Block(start) => format!("b{}", start),
+                    Target::Unknown => {
+                        let node = format!("u{}_{}", block.start, i);
+                        writeln!(f, "    {} [shape=ellipse, label=\"?\"];", node)?;
+                        node
+                    }
+                };
+                let style = match edge.kind {
+                    EdgeKind::Next | EdgeKind::Jump => "",
+                    EdgeKind::Taken => " [label=\"T\", color=darkgreen]",
+                    EdgeKind::NotTaken => " [label=\"F\", color=red]",
+                    EdgeKind::Return => " [label=\"return\", style=dashed]",
+                };
+                writeln!(f, "    b{} -> {}{};", block.start, to, style)?;
+            }
+        }
+        writeln!(f, "}}")
+    }
+}
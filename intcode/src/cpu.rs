@@ -0,0 +1,546 @@
+use crate::cache::{DecodeCache, Entry, Operand};
+use crate::cell::Cell;
+use crate::error::{ErrorKind, IntcodeError};
+use crate::io::{InputSource, OutputSink};
+use crate::memory::{Dense, Memory};
+use crate::observer::Observer;
+use crate::opcode::{decode, Opcode};
+use crate::overflow::Overflow;
+use crate::selfmod::{CodeTracker, CodeWrite};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status<C = i64> {
+    Output(C),
+    NeedsInput,
+    Halted,
+}
+
+impl<C> Status<C> {
+    pub fn output(self) -> Option<C> {
+        match self {
+            Status::Output(output) => Some(output),
+            _ => None,
+        }
+    }
+
+    pub fn map<D>(self, f: impl FnOnce(C) -> D) -> Status<D> {
+        match self {
+            Status::Output(output) => Status::Output(f(output)),
+            Status::NeedsInput => Status::NeedsInput,
+            Status::Halted => Status::Halted,
+        }
+    }
+}
+
+/// The resumable interface shared by the interpreter and transpiled
+/// programs, over cells of type `C`.
+pub trait Machine<C = i64> {
+    fn push_input(&mut self, value: C);
+
+    /// Runs until the program outputs a value, needs input it doesn't have
+    /// or halts.
+    fn run(&mut self) -> Result<Status<C>, IntcodeError>;
+
+    /// Like `run`, but returns `None` once about `steps` instructions ran
+    /// without a status, so the caller can check on other things before
+    /// resuming. Machines that can't stop partway run to the next status.
+    fn run_for(&mut self, steps: u64) -> Result<Option<Status<C>>, IntcodeError> {
+        let _ = steps;
+        self.run().map(Some)
+    }
+
+    /// Runs until the program halts, or needs input and `input` has none
+    /// for now. Input is pulled only when the program asks for it, and
+    /// every output goes to `output`.
+    fn run_io(
+        &mut self,
+        mut input: impl InputSource<C>,
+        mut output: impl OutputSink<C>,
+    ) -> Result<Status<C>, IntcodeError>
+    where
+        Self: Sized,
+    {
+        loop {
+            match self.run()? {
+                Status::Output(value) => output.output(value),
+                Status::NeedsInput => match input.next_input() {
+                    Some(value) => self.push_input(value),
+                    None => return Ok(Status::NeedsInput),
+                },
+                Status::Halted => return Ok(Status::Halted),
+            }
+        }
+    }
+}
+
+fn flag<C: Cell>(value: bool) -> C {
+    C::from_i64(value as i64).expect("every cell type holds 0 and 1")
+}
+
+/// An Intcode CPU over memory backend `M`, `Dense` unless chosen
+/// otherwise with `with_memory`. Its cells are the backend's, so
+/// `Cpu<Dense<i128>>` or `Cpu<Sparse<BigInt>>` run the same interpreter
+/// on wider integers.
+///
+/// Instructions in the loaded program are decoded once and cached. Clones
+/// share what was decoded so far, so cloning a freshly built CPU is the
+/// cheap way to run the same program many times.
+///
+/// Overflow in `add` and `mul` traps unless another policy is set with
+/// `set_overflow`. `detect_code_writes` turns on recording of stores into
+/// words that already ran as code.
+#[derive(Clone)]
+pub struct Cpu<M: Memory = Dense> {
+    pub(crate) pc: usize,
+    pub(crate) relative_offset: M::Cell,
+    pub(crate) mem: M,
+    pub(crate) input: VecDeque<M::Cell>,
+    pub(crate) cache: DecodeCache<M::Cell>,
+    pub(crate) overflow: Overflow,
+    pub(crate) code: Option<Box<CodeTracker<M::Cell>>>,
+}
+
+impl Cpu {
+    pub fn new(program: &[i64]) -> Self {
+        Self::with_memory(Dense::new(), program)
+    }
+}
+
+impl<M: Memory> Cpu<M> {
+    /// Loads `program` into `mem` and starts a CPU on it.
+    pub fn with_memory(mut mem: M, program: &[M::Cell]) -> Self {
+        mem.load(program);
+        Self {
+            pc: 0,
+            relative_offset: M::Cell::default(),
+            mem,
+            input: VecDeque::new(),
+            cache: DecodeCache::new(program.len()),
+            overflow: Overflow::default(),
+            code: None,
+        }
+    }
+
+    pub fn mem(&self) -> &M {
+        &self.mem
+    }
+
+    /// Gives write access to memory. Cached decodes are dropped, since
+    /// there is no telling which cells change.
+    pub fn mem_mut(&mut self) -> &mut M {
+        self.cache = DecodeCache::new(self.cache.len());
+        &mut self.mem
+    }
+
+    pub fn push_input(&mut self, value: M::Cell) {
+        self.input.push_back(value);
+    }
+
+    pub fn overflow(&self) -> Overflow {
+        self.overflow
+    }
+
+    pub fn set_overflow(&mut self, overflow: Overflow) {
+        self.overflow = overflow;
+    }
+
+    /// Starts or stops tracking which addresses execute as part of an
+    /// instruction, and recording every store that later changes one of
+    /// them. Stopping drops what was recorded.
+    pub fn detect_code_writes(&mut self, detect: bool) {
+        self.code = match (detect, self.code.take()) {
+            (true, None) => Some(Box::new(CodeTracker::new())),
+            (true, code) => code,
+            (false, _) => None,
+        };
+    }
+
+    /// The stores into executed code seen since `detect_code_writes` was
+    /// turned on, oldest first.
+    pub fn code_writes(&self) -> &[CodeWrite<M::Cell>] {
+        self.code.as_ref().map_or(&[], |code| &code.writes)
+    }
+
+    /// Returns and forgets the stores recorded so far.
+    pub fn take_code_writes(&mut self) -> Vec<CodeWrite<M::Cell>> {
+        self.code
+            .as_mut()
+            .map_or_else(Vec::new, |code| std::mem::take(&mut code.writes))
+    }
+
+    fn address(addr: &M::Cell) -> Result<usize, ErrorKind> {
+        addr.to_usize()
+            .ok_or_else(|| ErrorKind::InvalidAddress(addr.clamped()))
+    }
+
+    /// Checks that memory can hold a store to `addr`.
+    fn writable(&self, addr: usize) -> Result<usize, ErrorKind> {
+        match self.mem.fits(addr) {
+            true => Ok(addr),
+            false => Err(ErrorKind::InvalidAddress(
+                i64::try_from(addr).unwrap_or(i64::MAX),
+            )),
+        }
+    }
+
+    fn relative(&self, op: &M::Cell) -> Result<M::Cell, ErrorKind> {
+        self.relative_offset
+            .checked_add(op)
+            .ok_or_else(|| ErrorKind::InvalidAddress(op.clamped()))
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn relative_offset(&self) -> &M::Cell {
+        &self.relative_offset
+    }
+
+    pub fn pending_input(&self) -> &VecDeque<M::Cell> {
+        &self.input
+    }
+
+    fn read<O: Observer<M::Cell>>(&self, obs: &mut O, addr: usize) -> M::Cell {
+        let value = self.mem[addr].clone();
+        if O::ENABLED {
+            obs.read(addr, value.clone());
+        }
+        value
+    }
+
+    fn load<O: Observer<M::Cell>>(
+        &self,
+        obs: &mut O,
+        param: usize,
+        mode: i64,
+    ) -> Result<M::Cell, ErrorKind> {
+        let op = &self.mem[self.pc + 1 + param];
+        let value = match mode {
+            0 => self.read(obs, Self::address(op)?),
+            1 => op.clone(),
+            2 => self.read(obs, Self::address(&self.relative(op)?)?),
+            _ => return Err(ErrorKind::InvalidMode(mode)),
+        };
+        if O::ENABLED {
+            obs.param(param, value.clone());
+        }
+        Ok(value)
+    }
+
+    fn store<O: Observer<M::Cell>>(
+        &mut self,
+        obs: &mut O,
+        param: usize,
+        mode: i64,
+        value: M::Cell,
+    ) -> Result<(), ErrorKind> {
+        let op = &self.mem[self.pc + 1 + param];
+        let addr = match mode {
+            0 => Self::address(op)?,
+            1 => return Err(ErrorKind::ImmediateStore),
+            2 => Self::address(&self.relative(op)?)?,
+            _ => return Err(ErrorKind::InvalidMode(mode)),
+        };
+        let addr = self.writable(addr)?;
+
+        if O::ENABLED {
+            obs.write(addr, self.mem[addr].clone(), value.clone());
+        }
+        self.write(addr, value);
+        Ok(())
+    }
+
+    fn operand(&self, op: &Operand<M::Cell>) -> Result<M::Cell, ErrorKind> {
+        Ok(match op {
+            Operand::Immediate(value) => value.clone(),
+            Operand::Position(addr) => self.mem[*addr].clone(),
+            Operand::Relative(op) => self.mem[Self::address(&self.relative(op)?)?].clone(),
+        })
+    }
+
+    fn put(&mut self, op: &Operand<M::Cell>, value: M::Cell) -> Result<(), ErrorKind> {
+        let addr = match op {
+            Operand::Position(addr) => *addr,
+            Operand::Relative(op) => Self::address(&self.relative(op)?)?,
+            Operand::Immediate(_) => return Err(ErrorKind::ImmediateStore),
+        };
+        let addr = self.writable(addr)?;
+        self.cache.invalidate(addr);
+        self.mem[addr] = value;
+        Ok(())
+    }
+
+    fn write(&mut self, addr: usize, value: M::Cell) {
+        self.cache.invalidate(addr);
+        if let Some(code) = &mut self.code {
+            let old = &self.mem[addr];
+            if code.is_executed(addr) && *old != value {
+                code.writes.push(CodeWrite {
+                    pc: self.pc,
+                    addr,
+                    old: old.clone(),
+                    new: value.clone(),
+                });
+            }
+        }
+        self.mem[addr] = value;
+    }
+
+    /// Runs until the program outputs a value, reads from an empty input
+    /// queue or halts. A `NeedsInput` stop leaves `pc` on the input
+    /// instruction, so calling `run` again after `push_input` resumes it.
+    ///
+    /// On error, `pc` is left on the faulting instruction.
+    pub fn run(&mut self) -> Result<Status<M::Cell>, IntcodeError> {
+        self.run_with(&mut ())
+    }
+
+    /// Like `run`, but returns `None` after `steps` instructions without a
+    /// status.
+    pub fn run_for(&mut self, steps: u64) -> Result<Option<Status<M::Cell>>, IntcodeError> {
+        for _ in 0..steps {
+            if let Some(status) = self.step()? {
+                return Ok(Some(status));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Like `run`, but decodes every instruction afresh instead of going
+    /// through the decode cache. Kept as the reference to compare against.
+    pub fn run_uncached(&mut self) -> Result<Status<M::Cell>, IntcodeError> {
+        loop {
+            if let Some(status) = self.step_uncached(&mut ())? {
+                return Ok(status);
+            }
+        }
+    }
+
+    /// Like `run`, reporting every instruction to `obs`.
+    pub fn run_with<O: Observer<M::Cell>>(
+        &mut self,
+        obs: &mut O,
+    ) -> Result<Status<M::Cell>, IntcodeError> {
+        loop {
+            if let Some(status) = self.step_with(obs)? {
+                return Ok(status);
+            }
+        }
+    }
+
+    /// Executes a single instruction. Returns the status when it is an
+    /// output, a halt or an input with nothing queued, and `None` otherwise.
+    pub fn step(&mut self) -> Result<Option<Status<M::Cell>>, IntcodeError> {
+        self.step_with(&mut ())
+    }
+
+    /// Observed steps always take the uncached path, which reports every
+    /// parameter and memory access, and so do steps that track code
+    /// writes.
+    pub fn step_with<O: Observer<M::Cell>>(
+        &mut self,
+        obs: &mut O,
+    ) -> Result<Option<Status<M::Cell>>, IntcodeError> {
+        if !O::ENABLED && self.code.is_none() {
+            if let Some(entry) = self.cache.get(&self.mem, self.pc) {
+                // A cached instruction faults before changing any state, so
+                // on error running it again uncached reports the fault.
+                if let Ok(status) = self.execute_cached(entry) {
+                    return Ok(status);
+                }
+            }
+        }
+        self.step_uncached(obs)
+    }
+
+    fn step_uncached<O: Observer<M::Cell>>(
+        &mut self,
+        obs: &mut O,
+    ) -> Result<Option<Status<M::Cell>>, IntcodeError> {
+        let pc = self.pc;
+        let word = &self.mem[pc];
+        // An instruction word too wide for an `i64` can't be valid.
+        let instr = word.to_i64();
+        let reported = word.clamped();
+        let opcode = instr.and_then(|instr| Opcode::from_code(instr % 100));
+        if let (Some(code), Some(opcode)) = (&mut self.code, opcode) {
+            code.execute(pc, opcode.size());
+        }
+        if O::ENABLED {
+            let params = opcode.map_or(0, Opcode::params);
+            let words: [M::Cell; 4] = std::array::from_fn(|i| match i <= params {
+                true => self.mem[pc + i].clone(),
+                false => M::Cell::default(),
+            });
+            obs.instruction(pc, &words[..=params]);
+        }
+        let status = match instr {
+            Some(instr) => self.execute(obs, instr),
+            None => Err(ErrorKind::InvalidOpcode),
+        };
+        let status = status.map_err(|kind| {
+            self.pc = pc;
+            IntcodeError::new(pc, reported, kind)
+        })?;
+        if O::ENABLED && status != Some(Status::NeedsInput) {
+            obs.executed(pc);
+        }
+        Ok(status)
+    }
+
+    fn execute<O: Observer<M::Cell>>(
+        &mut self,
+        obs: &mut O,
+        instr: i64,
+    ) -> Result<Option<Status<M::Cell>>, ErrorKind> {
+        let (opcode, [mode_op1, mode_op2, mode_op3]) = decode(instr);
+
+        match opcode {
+            1 | 2 => {
+                let op1 = self.load(obs, 0, mode_op1)?;
+                let op2 = self.load(obs, 1, mode_op2)?;
+
+                let value = if opcode == 1 {
+                    self.overflow.add(&op1, &op2)?
+                } else {
+                    self.overflow.mul(&op1, &op2)?
+                };
+                self.store(obs, 2, mode_op3, value)?;
+
+                self.pc += 4;
+            }
+            3 => {
+                if let Some(input) = self.input.front().cloned() {
+                    self.store(obs, 0, mode_op1, input)?;
+                    self.input.pop_front();
+                    self.pc += 2;
+                } else {
+                    return Ok(Some(Status::NeedsInput));
+                }
+            }
+            4 => {
+                let output = self.load(obs, 0, mode_op1)?;
+                self.pc += 2;
+                return Ok(Some(Status::Output(output)));
+            }
+            5 => {
+                self.pc = if !self.load(obs, 0, mode_op1)?.is_zero() {
+                    Self::address(&self.load(obs, 1, mode_op2)?)?
+                } else {
+                    self.pc + 3
+                }
+            }
+            6 => {
+                self.pc = if self.load(obs, 0, mode_op1)?.is_zero() {
+                    Self::address(&self.load(obs, 1, mode_op2)?)?
+                } else {
+                    self.pc + 3
+                }
+            }
+            7 => {
+                let lt = self.load(obs, 0, mode_op1)? < self.load(obs, 1, mode_op2)?;
+                self.store(obs, 2, mode_op3, flag(lt))?;
+                self.pc += 4
+            }
+            8 => {
+                let eq = self.load(obs, 0, mode_op1)? == self.load(obs, 1, mode_op2)?;
+                self.store(obs, 2, mode_op3, flag(eq))?;
+                self.pc += 4
+            }
+            9 => {
+                self.relative_offset = self.relative(&self.load(obs, 0, mode_op1)?)?;
+                self.pc += 2;
+            }
+            99 => return Ok(Some(Status::Halted)),
+            _ => return Err(ErrorKind::InvalidOpcode),
+        }
+
+        Ok(None)
+    }
+
+    fn execute_cached(
+        &mut self,
+        entry: Entry<M::Cell>,
+    ) -> Result<Option<Status<M::Cell>>, ErrorKind> {
+        let [op1, op2, op3] = &entry.params;
+
+        match entry.opcode {
+            Opcode::Add => {
+                let value = self
+                    .overflow
+                    .add(&self.operand(op1)?, &self.operand(op2)?)?;
+                self.put(op3, value)?;
+                self.pc += 4;
+            }
+            Opcode::Mul => {
+                let value = self
+                    .overflow
+                    .mul(&self.operand(op1)?, &self.operand(op2)?)?;
+                self.put(op3, value)?;
+                self.pc += 4;
+            }
+            Opcode::In => {
+                if let Some(input) = self.input.front().cloned() {
+                    self.put(op1, input)?;
+                    self.input.pop_front();
+                    self.pc += 2;
+                } else {
+                    return Ok(Some(Status::NeedsInput));
+                }
+            }
+            Opcode::Out => {
+                let output = self.operand(op1)?;
+                self.pc += 2;
+                return Ok(Some(Status::Output(output)));
+            }
+            Opcode::Jnz => {
+                self.pc = if !self.operand(op1)?.is_zero() {
+                    Self::address(&self.operand(op2)?)?
+                } else {
+                    self.pc + 3
+                }
+            }
+            Opcode::Jz => {
+                self.pc = if self.operand(op1)?.is_zero() {
+                    Self::address(&self.operand(op2)?)?
+                } else {
+                    self.pc + 3
+                }
+            }
+            Opcode::Lt => {
+                let lt = self.operand(op1)? < self.operand(op2)?;
+                self.put(op3, flag(lt))?;
+                self.pc += 4;
+            }
+            Opcode::Eq => {
+                let eq = self.operand(op1)? == self.operand(op2)?;
+                self.put(op3, flag(eq))?;
+                self.pc += 4;
+            }
+            Opcode::Arb => {
+                self.relative_offset = self.relative(&self.operand(op1)?)?;
+                self.pc += 2;
+            }
+            Opcode::Hlt => return Ok(Some(Status::Halted)),
+        }
+
+        Ok(None)
+    }
+}
+
+impl<M: Memory> Machine<M::Cell> for Cpu<M> {
+    fn push_input(&mut self, value: M::Cell) {
+        Cpu::push_input(self, value)
+    }
+
+    fn run(&mut self) -> Result<Status<M::Cell>, IntcodeError> {
+        Cpu::run(self)
+    }
+
+    fn run_for(&mut self, steps: u64) -> Result<Option<Status<M::Cell>>, IntcodeError> {
+        Cpu::run_for(self, steps)
+    }
+}
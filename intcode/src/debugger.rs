@@ -0,0 +1,337 @@
+use crate::cpu::{Cpu, Status};
+use crate::disasm::Instruction;
+use crate::error::IntcodeError;
+use crate::memory::{Dense, Memory};
+use crate::observer::Observer;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Watch {
+    pub read: bool,
+    pub write: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read(i64),
+    Write { old: i64, new: i64 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hit {
+    pub pc: usize,
+    pub addr: usize,
+    pub access: Access,
+}
+
+/// Why execution stopped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Stop {
+    Stepped,
+    Breakpoint(usize),
+    Watchpoint(Vec<Hit>),
+    NeedsInput,
+    Halted,
+    Error(IntcodeError),
+}
+
+struct Watcher<'a> {
+    watchpoints: &'a BTreeMap<usize, Watch>,
+    pc: usize,
+    hits: Vec<Hit>,
+}
+
+impl Observer for Watcher<'_> {
+    fn instruction(&mut self, pc: usize, _words: &[i64]) {
+        self.pc = pc;
+    }
+
+    fn read(&mut self, addr: usize, value: i64) {
+        if self.watchpoints.get(&addr).is_some_and(|w| w.read) {
+            self.hits.push(Hit {
+                pc: self.pc,
+                addr,
+                access: Access::Read(value),
+            });
+        }
+    }
+
+    fn write(&mut self, addr: usize, old: i64, new: i64) {
+        if self.watchpoints.get(&addr).is_some_and(|w| w.write) {
+            self.hits.push(Hit {
+                pc: self.pc,
+                addr,
+                access: Access::Write { old, new },
+            });
+        }
+    }
+}
+
+pub struct Debugger<M: Memory<Cell = i64> = Dense> {
+    pub cpu: Cpu<M>,
+    pub breakpoints: BTreeSet<usize>,
+    pub watchpoints: BTreeMap<usize, Watch>,
+    pub outputs: Vec<i64>,
+}
+
+fn parse_num(s: Option<&str>) -> Option<i64> {
+    let s = s?;
+    match s.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn parse_addr(s: Option<&str>) -> Option<usize> {
+    parse_num(s).and_then(|n| if n >= 0 { Some(n as usize) } else { None })
+}
+
+/// The most words `x` dumps, or instructions `dis` lists, at once.
+const MAX_LISTING: usize = 4096;
+
+const HELP: &str = "\
+step [n]              execute n instructions (default 1)
+continue              run until a breakpoint, watchpoint, input request or halt
+break <pc>            set a breakpoint
+delete <pc>           remove a breakpoint
+watch <addr> [r|w|rw] stop when the program reads or writes addr (default rw)
+unwatch <addr>        remove a watchpoint
+rb                    print the relative base
+info                  print pc, relative base, queued input and stop points
+x <addr> [len]        dump len memory words from addr (default 8, at most 4096)
+dis [addr] [n]        disassemble n instructions from addr (default pc, 5)
+input <n>...          queue input values
+text <line>           queue a line of ASCII input, newline included
+quit                  exit the debugger
+";
+
+impl<M: Memory<Cell = i64>> Debugger<M> {
+    pub fn new(cpu: Cpu<M>) -> Self {
+        Self {
+            cpu,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeMap::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Executes one instruction, recording outputs and watchpoint hits.
+    pub fn step(&mut self) -> Stop {
+        let mut watcher = Watcher {
+            watchpoints: &self.watchpoints,
+            pc: self.cpu.pc(),
+            hits: Vec::new(),
+        };
+        let status = self.cpu.step_with(&mut watcher);
+        let hits = watcher.hits;
+        match status {
+            Err(e) => return Stop::Error(e),
+            Ok(Some(Status::Output(value))) => self.outputs.push(value),
+            Ok(Some(Status::NeedsInput)) => return Stop::NeedsInput,
+            Ok(Some(Status::Halted)) => return Stop::Halted,
+            Ok(None) => {}
+        }
+        if !hits.is_empty() {
+            Stop::Watchpoint(hits)
+        } else {
+            Stop::Stepped
+        }
+    }
+
+    /// Runs until something other than a plain step stops execution. A
+    /// breakpoint on the current pc is stepped over.
+    pub fn cont(&mut self) -> Stop {
+        loop {
+            match self.step() {
+                Stop::Stepped => {}
+                stop => return stop,
+            }
+            if self.breakpoints.contains(&self.cpu.pc()) {
+                return Stop::Breakpoint(self.cpu.pc());
+            }
+        }
+    }
+
+    /// Decodes the instruction at `addr` as the CPU would see it.
+    pub fn instruction_at(&self, addr: usize) -> Option<Instruction> {
+        let words: Vec<i64> = (0..4)
+            .map(|i| self.cpu.mem()[addr.saturating_add(i)])
+            .collect();
+        Instruction::decode(&words, 0).map(|instr| Instruction { addr, ..instr })
+    }
+
+    fn write_instruction(&self, out: &mut impl Write, addr: usize) -> io::Result<usize> {
+        match self.instruction_at(addr) {
+            Some(instr) => {
+                writeln!(out, "{:>6}: {}", addr, instr)?;
+                Ok(instr.size())
+            }
+            None => {
+                writeln!(out, "{:>6}: .data {}", addr, self.cpu.mem()[addr])?;
+                Ok(1)
+            }
+        }
+    }
+
+    fn write_stop(&mut self, out: &mut impl Write, stop: Stop) -> io::Result<()> {
+        for value in self.outputs.drain(..) {
+            if (32..127).contains(&value) || value == 10 {
+                writeln!(out, "output: {} ({:?})", value, value as u8 as char)?;
+            } else {
+                writeln!(out, "output: {}", value)?;
+            }
+        }
+        match stop {
+            Stop::Stepped => {}
+            Stop::Breakpoint(pc) => writeln!(out, "breakpoint at {}", pc)?,
+            Stop::Watchpoint(hits) => {
+                for hit in hits {
+                    match hit.access {
+                        Access::Read(value) => writeln!(
+                            out,
+                            "watchpoint {}: read {} at pc {}",
+                            hit.addr, value, hit.pc
+                        )?,
+                        Access::Write { old, new } => writeln!(
+                            out,
+                            "watchpoint {}: write {} -> {} at pc {}",
+                            hit.addr, old, new, hit.pc
+                        )?,
+                    }
+                }
+            }
+            Stop::NeedsInput => writeln!(out, "waiting for input")?,
+            Stop::Halted => writeln!(out, "halted")?,
+            Stop::Error(e) => writeln!(out, "error: {}", e)?,
+        }
+        self.write_instruction(out, self.cpu.pc())?;
+        Ok(())
+    }
+
+    /// Executes one REPL command, writing its result to `out`. Returns
+    /// `false` once the user asks to quit.
+    pub fn command(&mut self, line: &str, out: &mut impl Write) -> io::Result<bool> {
+        let mut words = line.split_whitespace();
+        let cmd = match words.next() {
+            Some(cmd) => cmd,
+            None => return Ok(true),
+        };
+
+        match cmd {
+            "s" | "step" => {
+                let n = parse_num(words.next()).unwrap_or(1);
+                let mut stop = Stop::Stepped;
+                for _ in 0..n {
+                    stop = self.step();
+                    if stop != Stop::Stepped {
+                        break;
+                    }
+                }
+                self.write_stop(out, stop)?;
+            }
+            "c" | "continue" => {
+                let stop = self.cont();
+                self.write_stop(out, stop)?;
+            }
+            "b" | "break" => match parse_addr(words.next()) {
+                Some(pc) => {
+                    self.breakpoints.insert(pc);
+                    writeln!(out, "breakpoint at {}", pc)?;
+                }
+                None => writeln!(out, "usage: break <pc>")?,
+            },
+            "d" | "delete" => match parse_addr(words.next()) {
+                Some(pc) if self.breakpoints.remove(&pc) => {}
+                _ => writeln!(out, "no such breakpoint")?,
+            },
+            "w" | "watch" => {
+                let addr = parse_addr(words.next());
+                let watch = match words.next().unwrap_or("rw") {
+                    "r" => Some(Watch {
+                        read: true,
+                        write: false,
+                    }),
+                    "w" => Some(Watch {
+                        read: false,
+                        write: true,
+                    }),
+                    "rw" => Some(Watch {
+                        read: true,
+                        write: true,
+                    }),
+                    _ => None,
+                };
+                match (addr, watch) {
+                    (Some(addr), Some(watch)) => {
+                        self.watchpoints.insert(addr, watch);
+                        writeln!(out, "watching {}", addr)?;
+                    }
+                    _ => writeln!(out, "usage: watch <addr> [r|w|rw]")?,
+                }
+            }
+            "unwatch" => match parse_addr(words.next()) {
+                Some(addr) if self.watchpoints.remove(&addr).is_some() => {}
+                _ => writeln!(out, "no such watchpoint")?,
+            },
+            "rb" => writeln!(out, "relative base: {}", self.cpu.relative_offset())?,
+            "i" | "info" => {
+                writeln!(out, "pc: {}", self.cpu.pc())?;
+                writeln!(out, "relative base: {}", self.cpu.relative_offset())?;
+                writeln!(out, "input: {:?}", self.cpu.pending_input())?;
+                writeln!(out, "breakpoints: {:?}", self.breakpoints)?;
+                writeln!(out, "watchpoints: {:?}", self.watchpoints)?;
+            }
+            "x" => {
+                let addr = parse_addr(words.next());
+                let len = words.next().map_or(Some(8), |w| parse_addr(Some(w)));
+                match (addr, len) {
+                    (Some(_), Some(len)) if len > MAX_LISTING => {
+                        writeln!(out, "at most {} words at a time", MAX_LISTING)?
+                    }
+                    (Some(addr), Some(len)) => match addr.checked_add(len) {
+                        Some(end) => {
+                            for row in (addr..end).step_by(8) {
+                                let values: Vec<_> = (row..row.saturating_add(8).min(end))
+                                    .map(|a| self.cpu.mem()[a].to_string())
+                                    .collect();
+                                writeln!(out, "{:>6}: {}", row, values.join(" "))?;
+                            }
+                        }
+                        None => writeln!(out, "address out of range")?,
+                    },
+                    _ => writeln!(out, "usage: x <addr> [len]")?,
+                }
+            }
+            "dis" => {
+                let mut addr = parse_addr(words.next()).unwrap_or_else(|| self.cpu.pc());
+                let n = parse_addr(words.next()).unwrap_or(5).min(MAX_LISTING);
+                for _ in 0..n {
+                    let size = self.write_instruction(out, addr)?;
+                    match addr.checked_add(size) {
+                        Some(next) => addr = next,
+                        None => break,
+                    }
+                }
+            }
+            "input" => {
+                let values: Option<Vec<i64>> = words.map(|w| parse_num(Some(w))).collect();
+                match values {
+                    Some(values) => values.into_iter().for_each(|v| self.cpu.push_input(v)),
+                    None => writeln!(out, "usage: input <n>...")?,
+                }
+            }
+            "text" => {
+                let text = line.trim_start()[cmd.len()..].trim_start();
+                for b in text.bytes().chain(Some(b'\n')) {
+                    self.cpu.push_input(b as i64);
+                }
+            }
+            "h" | "help" => write!(out, "{}", HELP)?,
+            "q" | "quit" => return Ok(false),
+            _ => writeln!(out, "unknown command: {} (try help)", cmd)?,
+        }
+
+        Ok(true)
+    }
+}
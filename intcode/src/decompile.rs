@@ -0,0 +1,1041 @@
+//! Turns a program's control-flow graph into structured pseudo-code, one
+//! function per callee of the `call_return` idiom.
+//!
+//! Functions keep their frame with `arb #n` on entry and `arb #-n` before
+//! jumping back through `[rb]`, where the caller stored the return address.
+//! Slots of the frame show up as `arg`s, which callers write to just
+//! before the call, and `local`s; the callee's next frame, which a function
+//! fills to make a call, shows up as `out`s. Branches are structured by
+//! address, the way a compiler lays them out, and anything that doesn't
+//! fit falls back to a `goto`.
+
+use crate::cfg::{self, Block, Cfg, EdgeKind, Target};
+use crate::disasm::{Flow, Instruction, Param};
+use crate::opcode::{Mode, Opcode};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Lt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl Op {
+    fn negate(self) -> Self {
+        match self {
+            Op::Lt => Op::Ge,
+            Op::Ge => Op::Lt,
+            Op::Eq => Op::Ne,
+            Op::Ne => Op::Eq,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Op::Lt => "<",
+            Op::Ge => ">=",
+            Op::Eq => "==",
+            Op::Ne => "!=",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Cond {
+    lhs: String,
+    op: Op,
+    rhs: String,
+}
+
+impl Cond {
+    fn negate(&self) -> Self {
+        Self {
+            op: self.op.negate(),
+            ..self.clone()
+        }
+    }
+}
+
+impl fmt::Display for Cond {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.op, self.rhs.as_str()) {
+            (Op::Ne, "0") => write!(f, "{}", self.lhs),
+            (Op::Eq, "0") => write!(f, "!{}", self.lhs),
+            _ => write!(f, "{} {} {}", self.lhs, self.op.symbol(), self.rhs),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Stmt {
+    Label(usize),
+    Assign {
+        dst: String,
+        value: String,
+    },
+    Call {
+        target: String,
+        args: Vec<String>,
+        result: Option<String>,
+    },
+    /// Anything else that reads like one line.
+    Line(String),
+    Goto(usize),
+    Break,
+    Continue,
+    Return,
+    If {
+        cond: Cond,
+        then: Vec<Stmt>,
+        els: Vec<Stmt>,
+    },
+    Loop(Vec<Stmt>),
+    While {
+        cond: Cond,
+        body: Vec<Stmt>,
+    },
+    DoWhile {
+        body: Vec<Stmt>,
+        cond: Cond,
+    },
+}
+
+struct Function {
+    entry: usize,
+    /// Addresses of the function's blocks, in order.
+    blocks: Vec<usize>,
+    /// The size of the frame set up on entry; zero for `main`.
+    frame: i64,
+    args: i64,
+    /// Whether the function writes its first slot, where callers look for
+    /// a result.
+    returns: bool,
+    body: Vec<Stmt>,
+}
+
+pub struct Decompilation {
+    functions: Vec<Function>,
+}
+
+/// Decompiles `program`, starting at pc 0.
+pub fn decompile(program: &[i64]) -> Decompilation {
+    Decompilation::new(&cfg::build(program))
+}
+
+fn name(entry: usize) -> String {
+    if entry == 0 {
+        "main".to_string()
+    } else {
+        format!("f{}", entry)
+    }
+}
+
+/// The frame size an `arb` at the start of a function sets up.
+fn frame_size(block: &Block) -> Option<i64> {
+    let instr = block.instructions.first()?;
+    match instr.params.first() {
+        Some(param) if instr.opcode == Opcode::Arb && param.mode == Mode::Immediate => {
+            Some(param.value).filter(|&size| size > 0)
+        }
+        _ => None,
+    }
+}
+
+/// The highest slot of its frame that a function reads before writing it
+/// in its first block. Callers don't always fill such a slot right before
+/// the call; often it holds the result of the previous one.
+fn entry_args(block: &Block, frame: i64) -> i64 {
+    let own_slot = |param: &Param| {
+        Some(frame + param.value)
+            .filter(|&slot| param.mode == Mode::Relative && param.value < 0 && slot > 0)
+    };
+    let mut filled = BTreeSet::new();
+    let mut args = 0;
+    // Past the `arb` that sets the frame up.
+    for instr in block.instructions.iter().skip(1) {
+        for slot in reads(instr).filter_map(own_slot) {
+            if !filled.contains(&slot) {
+                args = args.max(slot);
+            }
+        }
+        filled.extend(written(instr).and_then(own_slot));
+    }
+    args
+}
+
+/// The slot of the next frame that `param` names, if any.
+fn out_slot(param: &Param) -> Option<i64> {
+    Some(param.value).filter(|&slot| param.mode == Mode::Relative && slot > 0)
+}
+
+fn reads(instr: &Instruction) -> impl Iterator<Item = &Param> {
+    let write = instr.opcode.write_param();
+    instr
+        .params
+        .iter()
+        .enumerate()
+        .filter(move |&(i, _)| Some(i) != write)
+        .map(|(_, param)| param)
+}
+
+fn written(instr: &Instruction) -> Option<&Param> {
+    instr.opcode.write_param().map(|i| &instr.params[i])
+}
+
+/// The instructions right before a call that fill the callee's frame,
+/// from the last one back.
+fn call_args(block: &Block) -> impl Iterator<Item = &Instruction> {
+    let len = block.instructions.len();
+    block.instructions[..len.saturating_sub(2)]
+        .iter()
+        .rev()
+        .take_while(|instr| {
+            written(instr).and_then(out_slot).is_some()
+                && reads(instr).all(|param| out_slot(param).is_none())
+        })
+}
+
+impl Decompilation {
+    fn new(cfg: &Cfg) -> Self {
+        let program = &cfg.disassembly.program;
+        let instructions = &cfg.disassembly.instructions;
+        let patched: BTreeSet<usize> = instructions
+            .values()
+            .filter_map(written)
+            .filter(|param| param.mode == Mode::Position)
+            .filter_map(|param| usize::try_from(param.value).ok())
+            .filter(|&addr| {
+                let code = instructions.range(..=addr).next_back();
+                code.is_some_and(|(_, instr)| addr < instr.addr + instr.size())
+            })
+            .collect();
+
+        let mut callees = BTreeSet::new();
+        let mut args: BTreeMap<usize, i64> = BTreeMap::new();
+        for block in cfg.blocks.values() {
+            if block.call_return(program).is_none() {
+                continue;
+            }
+            if let Flow::Jump(callee) = block.last().flow() {
+                let jump = block.last();
+                if !cfg.blocks.contains_key(&callee) || patched.contains(&(jump.addr + 2)) {
+                    continue;
+                }
+                callees.insert(callee);
+                let count = call_args(block)
+                    .filter_map(|instr| written(instr).and_then(out_slot))
+                    .max()
+                    .unwrap_or(0);
+                let entry = args.entry(callee).or_insert(0);
+                *entry = (*entry).max(count);
+            }
+        }
+
+        let mut functions = Vec::new();
+        for entry in std::iter::once(0).chain(callees.iter().copied()) {
+            if !cfg.blocks.contains_key(&entry) {
+                continue;
+            }
+            let blocks = members(cfg, entry);
+            let frame = if entry == 0 {
+                0
+            } else {
+                frame_size(&cfg.blocks[&entry]).unwrap_or(0)
+            };
+            let returns = frame > 1
+                && blocks.iter().any(|start| {
+                    cfg.blocks[start].instructions.iter().any(|instr| {
+                        written(instr).is_some_and(|param| {
+                            param.mode == Mode::Relative && param.value == 1 - frame
+                        })
+                    })
+                });
+            let callers = args.get(&entry).copied().unwrap_or(0);
+            let reads = if frame > 0 {
+                entry_args(&cfg.blocks[&entry], frame)
+            } else {
+                0
+            };
+            functions.push(Function {
+                entry,
+                blocks,
+                frame,
+                args: callers.max(reads).min((frame - 1).max(0)),
+                returns,
+                body: Vec::new(),
+            });
+        }
+
+        let returns: BTreeMap<usize, bool> = functions
+            .iter()
+            .map(|function| (function.entry, function.returns))
+            .collect();
+        let signatures: BTreeMap<usize, i64> = functions
+            .iter()
+            .map(|function| (function.entry, function.args))
+            .collect();
+        for function in &mut functions {
+            let body = {
+                let mut structurer = Structurer {
+                    cfg,
+                    function,
+                    signatures: &signatures,
+                    patched: &patched,
+                    loops: Vec::new(),
+                };
+                structurer.range(0, function.blocks.len(), None, None)
+            };
+            let mut targets = BTreeSet::new();
+            gotos(&body, &mut targets);
+            function.body = tidy(drop_labels(body, &targets), &returns);
+        }
+        Self { functions }
+    }
+}
+
+/// The blocks reachable from `entry` without following calls.
+fn members(cfg: &Cfg, entry: usize) -> Vec<usize> {
+    let program = &cfg.disassembly.program;
+    let mut seen = BTreeSet::new();
+    let mut todo = vec![entry];
+    while let Some(start) = todo.pop() {
+        if !seen.insert(start) {
+            continue;
+        }
+        let block = &cfg.blocks[&start];
+        let call = block.call_return(program).is_some();
+        for edge in &block.edges {
+            match edge.target {
+                Target::Block(_) if call && edge.kind == EdgeKind::Jump => {}
+                Target::Block(target) => todo.push(target),
+                Target::Unknown => {}
+            }
+        }
+    }
+    seen.into_iter().collect()
+}
+
+struct Structurer<'a> {
+    cfg: &'a Cfg,
+    function: &'a Function,
+    signatures: &'a BTreeMap<usize, i64>,
+    /// Parameter cells that some instruction writes over.
+    patched: &'a BTreeSet<usize>,
+    /// The enclosing loops, innermost last: where `continue` and `break`
+    /// go.
+    loops: Vec<(usize, Option<usize>)>,
+}
+
+impl<'a> Structurer<'a> {
+    fn block(&self, index: usize) -> &'a Block {
+        &self.cfg.blocks[&self.function.blocks[index]]
+    }
+
+    fn index(&self, addr: usize) -> Option<usize> {
+        self.function.blocks.binary_search(&addr).ok()
+    }
+
+    fn operand(&self, param: &Param) -> String {
+        let frame = self.function.frame;
+        match param.mode {
+            Mode::Immediate => param.value.to_string(),
+            Mode::Position => format!("mem[{}]", param.value),
+            Mode::Relative if param.value > 0 => format!("out{}", param.value),
+            Mode::Relative if frame > 0 && param.value < 0 && param.value >= -frame => {
+                let slot = frame + param.value;
+                let args = self.function.args;
+                if slot == 0 {
+                    "return_address".to_string()
+                } else if slot <= args {
+                    format!("arg{}", slot)
+                } else {
+                    format!("local{}", slot - args)
+                }
+            }
+            Mode::Relative => param.to_string(),
+        }
+    }
+
+    /// Adds the statements for `instrs`, the start of `block`. The frame's
+    /// setup and teardown go without saying.
+    fn statements(&self, block: &Block, mut instrs: &[Instruction], stmts: &mut Vec<Stmt>) {
+        let frame = self.function.frame;
+        let is_arb = |instr: &Instruction, size: i64| {
+            instr.opcode == Opcode::Arb && self.immediate(instr, 0) == Some(size)
+        };
+        if frame > 0 && block.start == self.function.entry {
+            if let [first, rest @ ..] = instrs {
+                if is_arb(first, frame) {
+                    instrs = rest;
+                }
+            }
+        }
+        if self.is_return(block) {
+            if let [rest @ .., last] = instrs {
+                if is_arb(last, -frame) {
+                    instrs = rest;
+                }
+            }
+        }
+        stmts.extend(instrs.iter().map(|instr| self.statement(instr)));
+    }
+
+    /// Whether some instruction writes over parameter `i` of `instr`.
+    fn is_patched(&self, instr: &Instruction, i: usize) -> bool {
+        self.patched.contains(&(instr.addr + 1 + i))
+    }
+
+    /// Parameter `i` of `instr`. One that the program patches holds
+    /// whatever was last written over it.
+    fn param(&self, instr: &Instruction, i: usize) -> String {
+        let param = &instr.params[i];
+        if !self.is_patched(instr, i) {
+            return self.operand(param);
+        }
+        let cell = format!("mem[{}]", instr.addr + 1 + i);
+        match param.mode {
+            Mode::Immediate => cell,
+            Mode::Position => format!("mem[{}]", cell),
+            Mode::Relative => format!("mem[rb + {}]", cell),
+        }
+    }
+
+    /// The constant parameter `i` of `instr` holds, if any.
+    fn immediate(&self, instr: &Instruction, i: usize) -> Option<i64> {
+        let param = &instr.params[i];
+        Some(param.value).filter(|_| param.mode == Mode::Immediate && !self.is_patched(instr, i))
+    }
+
+    /// The value an `add` or `mul` computes, folding identities.
+    fn arithmetic(&self, instr: &Instruction) -> String {
+        let (a, b) = (self.param(instr, 0), self.param(instr, 1));
+        let (x, y) = (self.immediate(instr, 0), self.immediate(instr, 1));
+        match (instr.opcode, x, y) {
+            (Opcode::Add, _, Some(0)) => a,
+            (Opcode::Add, Some(0), _) => b,
+            (Opcode::Add, _, Some(y)) if y < 0 => format!("{} - {}", a, y.unsigned_abs()),
+            (Opcode::Add, _, _) => format!("{} + {}", a, b),
+            (_, _, Some(1)) => a,
+            (_, Some(1), _) => b,
+            (_, _, Some(-1)) => format!("-{}", a),
+            (_, Some(-1), _) => format!("-{}", b),
+            _ => format!("{} * {}", a, b),
+        }
+    }
+
+    fn compare(&self, instr: &Instruction) -> Cond {
+        Cond {
+            lhs: self.param(instr, 0),
+            op: if instr.opcode == Opcode::Lt {
+                Op::Lt
+            } else {
+                Op::Eq
+            },
+            rhs: self.param(instr, 1),
+        }
+    }
+
+    /// What an instruction that writes a cell writes to it.
+    fn value(&self, instr: &Instruction) -> String {
+        match instr.opcode {
+            Opcode::Add | Opcode::Mul => self.arithmetic(instr),
+            Opcode::Lt | Opcode::Eq => self.compare(instr).to_string(),
+            _ => "input()".to_string(),
+        }
+    }
+
+    /// The decoded instruction that covers `addr`, if any.
+    fn code_at(&self, addr: i64) -> Option<&Instruction> {
+        let addr = usize::try_from(addr).ok()?;
+        let (_, instr) = self
+            .cfg
+            .disassembly
+            .instructions
+            .range(..=addr)
+            .next_back()?;
+        Some(instr).filter(|instr| addr < instr.addr + instr.size())
+    }
+
+    fn statement(&self, instr: &Instruction) -> Stmt {
+        if let Some(param) = written(instr) {
+            let write = instr.opcode.write_param().unwrap();
+            let patched = Some(param)
+                .filter(|param| param.mode == Mode::Position && !self.is_patched(instr, write))
+                .and_then(|param| self.code_at(param.value));
+            let (dst, value) = (self.param(instr, write), self.value(instr));
+            return match patched {
+                Some(code) => Stmt::Line(format!(
+                    "{} = {}  ; patches the instruction at {}",
+                    dst, value, code.addr
+                )),
+                None => Stmt::Assign { dst, value },
+            };
+        }
+        match instr.opcode {
+            Opcode::Out => Stmt::Line(format!("output({})", self.param(instr, 0))),
+            Opcode::Arb => Stmt::Line(format!("rb += {}", self.param(instr, 0))),
+            Opcode::Hlt => Stmt::Line("halt".to_string()),
+            // Only reached for jumps that never go anywhere.
+            _ => Stmt::Line(instr.to_string()),
+        }
+    }
+
+    /// When the block's last instruction jumps, the condition under which
+    /// it does, and whether the compare before it folded into it.
+    fn condition(&self, block: &Block) -> (Cond, bool) {
+        let jump = block.last();
+        let (cond, taken) = (&jump.params[0], jump.opcode == Opcode::Jnz);
+        let dead = || {
+            self.is_temporary(cond)
+                || !self.live(
+                    cond,
+                    block.start,
+                    block.instructions.len(),
+                    &mut BTreeSet::new(),
+                )
+        };
+        let compare = block.instructions.iter().rev().nth(1).filter(|instr| {
+            matches!(instr.opcode, Opcode::Lt | Opcode::Eq)
+                && written(instr) == Some(cond)
+                && cond.mode != Mode::Immediate
+                && dead()
+        });
+        match compare {
+            Some(compare) => {
+                let cond = self.compare(compare);
+                (if taken { cond } else { cond.negate() }, true)
+            }
+            None => (
+                Cond {
+                    lhs: self.param(jump, 0),
+                    op: if taken { Op::Ne } else { Op::Eq },
+                    rhs: "0".to_string(),
+                },
+                false,
+            ),
+        }
+    }
+
+    /// Whether every read of the cell `param` names, anywhere in the
+    /// program, is a jump right after a compare that writes it: the
+    /// compiler's scratch cell for conditions.
+    fn is_temporary(&self, param: &Param) -> bool {
+        if param.mode != Mode::Position {
+            return false;
+        }
+        let mut previous: Option<&Instruction> = None;
+        for instr in self.cfg.disassembly.instructions.values() {
+            if reads(instr).any(|read| read == param) {
+                let jump = matches!(instr.opcode, Opcode::Jnz | Opcode::Jz)
+                    && instr.params[0] == *param
+                    && instr.params[1] != *param;
+                let compared = previous.is_some_and(|prev| {
+                    matches!(prev.opcode, Opcode::Lt | Opcode::Eq)
+                        && prev.addr + prev.size() == instr.addr
+                        && written(prev) == Some(param)
+                });
+                if !(jump && compared) {
+                    return false;
+                }
+            }
+            previous = Some(instr);
+        }
+        true
+    }
+
+    /// Whether the block ends by returning from the function.
+    fn is_return(&self, block: &Block) -> bool {
+        let last = block.last();
+        self.function.frame > 0
+            && last.flow() == Flow::Indirect
+            && last.params[1]
+                == Param {
+                    mode: Mode::Relative,
+                    value: 0,
+                }
+    }
+
+    /// Whether `param` may be read from instruction `from` of the block at
+    /// `start` on, before anything writes it.
+    fn live(&self, param: &Param, start: usize, from: usize, seen: &mut BTreeSet<usize>) -> bool {
+        let block = &self.cfg.blocks[&start];
+        for instr in &block.instructions[from..] {
+            if reads(instr).any(|read| read == param) {
+                return true;
+            }
+            if written(instr) == Some(param) {
+                return false;
+            }
+        }
+        // Only the function's own frame is out of a callee's reach.
+        let own = param.mode == Mode::Relative && param.value < 0;
+        let call = block.call_return(&self.cfg.disassembly.program).is_some();
+        if call && !own {
+            return true;
+        }
+        block.edges.iter().any(|edge| match edge.target {
+            Target::Block(_) if call && edge.kind == EdgeKind::Jump => false,
+            Target::Block(target) if self.index(target).is_none() => true,
+            Target::Block(target) => seen.insert(target) && self.live(param, target, 0, seen),
+            Target::Unknown => !(own && self.is_return(block)),
+        })
+    }
+
+    /// Jumps to `next` when the block at `i` falls through to it but the
+    /// statements after its own don't start there.
+    fn fall_through(
+        &self,
+        i: usize,
+        hi: usize,
+        follow: Option<usize>,
+        next: usize,
+        stmts: &mut Vec<Stmt>,
+    ) {
+        let after = if i + 1 < hi {
+            self.function.blocks.get(i + 1).copied()
+        } else {
+            follow
+        };
+        if after != Some(next) && self.index(next).is_some() {
+            stmts.push(self.jump(next));
+        }
+    }
+
+    fn jump(&self, target: usize) -> Stmt {
+        match self.loops.last() {
+            Some(&(head, _)) if head == target => Stmt::Continue,
+            Some(&(_, Some(exit))) if exit == target => Stmt::Break,
+            _ => Stmt::Goto(target),
+        }
+    }
+
+    /// Whether the function's block at `index` ends by jumping back to
+    /// the one at `head`.
+    fn jumps_back(&self, index: usize, head: usize) -> bool {
+        let block = self.block(index);
+        block.call_return(&self.cfg.disassembly.program).is_none()
+            && block.edges.iter().any(|edge| {
+                edge.target == Target::Block(head)
+                    && matches!(edge.kind, EdgeKind::Jump | EdgeKind::Taken)
+            })
+    }
+
+    /// Structures the blocks `lo..hi`; running off the end goes on to
+    /// `follow`. `looped` is the block whose loop is already being
+    /// structured.
+    fn range(
+        &mut self,
+        lo: usize,
+        hi: usize,
+        follow: Option<usize>,
+        looped: Option<usize>,
+    ) -> Vec<Stmt> {
+        let mut stmts = Vec::new();
+        let mut i = lo;
+        while i < hi {
+            let start = self.function.blocks[i];
+            let back = (i..hi).rev().find(|&j| self.jumps_back(j, start));
+            if let Some(j) = back.filter(|_| looped != Some(i)) {
+                let exit = self.function.blocks.get(j + 1).copied();
+                self.loops.push((start, exit));
+                let mut body = self.range(i, j + 1, Some(start), Some(i));
+                self.loops.pop();
+                if let Some(Stmt::Continue) = body.last() {
+                    body.pop();
+                }
+                stmts.push(Stmt::Loop(body));
+                i = j + 1;
+                continue;
+            }
+
+            stmts.push(Stmt::Label(start));
+            i = self.block_statements(i, hi, follow, &mut stmts);
+        }
+        stmts
+    }
+
+    /// Adds the statements of the block at `i` and whatever it branches
+    /// around, and returns the index to go on from.
+    fn block_statements(
+        &mut self,
+        i: usize,
+        hi: usize,
+        follow: Option<usize>,
+        stmts: &mut Vec<Stmt>,
+    ) -> usize {
+        let program = &self.cfg.disassembly.program;
+        let block = self.block(i);
+        let instructions = &block.instructions;
+        let last = block.last();
+        let next = block.end();
+        // Whether a forward jump to `target` stays in the range.
+        let reaches = |target: usize, index: Option<usize>| match index {
+            Some(index) => index > i && (index < hi || (index == hi && follow == Some(target))),
+            None => false,
+        };
+
+        if let Some(ret) = block.call_return(program) {
+            let count = instructions.len();
+            let (target, arity) = match last.flow() {
+                Flow::Jump(callee) if !self.is_patched(last, 1) => (
+                    name(callee),
+                    self.signatures.get(&callee).copied().unwrap_or(0),
+                ),
+                _ => (format!("(*{})", self.param(last, 1)), 0),
+            };
+            let mut args = vec![None; arity as usize];
+            let mut folded = 0;
+            for instr in call_args(block) {
+                let slot = written(instr).and_then(out_slot).unwrap();
+                match args.get_mut(slot as usize - 1) {
+                    Some(arg @ None) => *arg = Some(self.value(instr)),
+                    _ => break,
+                }
+                folded += 1;
+            }
+            self.statements(block, &instructions[..count - 2 - folded], stmts);
+            stmts.push(Stmt::Call {
+                target,
+                args: args
+                    .into_iter()
+                    .enumerate()
+                    .map(|(slot, arg)| arg.unwrap_or_else(|| format!("out{}", slot + 1)))
+                    .collect(),
+                result: None,
+            });
+            self.fall_through(i, hi, follow, ret, stmts);
+            return i + 1;
+        }
+
+        let flow = last.flow();
+        let condition = match flow {
+            Flow::Branch(_) | Flow::IndirectBranch => Some(self.condition(block)),
+            _ => None,
+        };
+        let skip = match (flow, &condition) {
+            (_, Some((_, true))) => 2,
+            (Flow::Jump(_), _) | (Flow::Indirect, _) | (_, Some(_)) => 1,
+            _ => 0,
+        };
+        let cond = condition.map(|(cond, _)| cond);
+        let returns = self.is_return(block);
+        self.statements(block, &instructions[..instructions.len() - skip], stmts);
+
+        match flow {
+            Flow::Next => {
+                self.fall_through(i, hi, follow, next, stmts);
+                i + 1
+            }
+            Flow::Halt => i + 1,
+            Flow::Jump(target) => {
+                if !(i + 1 == hi && follow == Some(target)) {
+                    stmts.push(self.jump(target));
+                }
+                i + 1
+            }
+            Flow::Indirect if returns => {
+                stmts.push(Stmt::Return);
+                i + 1
+            }
+            Flow::Indirect => {
+                stmts.push(Stmt::Line(format!("goto *{}", self.param(last, 1))));
+                i + 1
+            }
+            Flow::IndirectBranch => {
+                let goto = Stmt::Line(format!("goto *{}", self.param(last, 1)));
+                stmts.push(Stmt::If {
+                    cond: cond.unwrap(),
+                    then: vec![goto],
+                    els: Vec::new(),
+                });
+                self.fall_through(i, hi, follow, next, stmts);
+                i + 1
+            }
+            Flow::Branch(target) => {
+                let cond = cond.unwrap();
+                let ti = self.index(target);
+                if !reaches(target, ti) {
+                    stmts.push(Stmt::If {
+                        cond,
+                        then: vec![self.jump(target)],
+                        els: Vec::new(),
+                    });
+                    self.fall_through(i, hi, follow, next, stmts);
+                    return i + 1;
+                }
+
+                // if (!cond) { then } else { els }, where the then part
+                // ends by jumping over the else part.
+                let ti = ti.unwrap();
+                let merge = match self.block_flow(ti - 1) {
+                    Some(Flow::Jump(merge)) if ti - 1 > i => Some(merge)
+                        .filter(|&merge| reaches(merge, self.index(merge)))
+                        .filter(|&merge| merge > target),
+                    _ => None,
+                };
+                match merge {
+                    Some(merge) => {
+                        let mi = self.index(merge).unwrap();
+                        let then = self.range(i + 1, ti, Some(merge), None);
+                        let els = self.range(ti, mi, Some(merge), None);
+                        stmts.push(Stmt::If {
+                            cond: cond.negate(),
+                            then,
+                            els,
+                        });
+                        mi
+                    }
+                    None => {
+                        let then = self.range(i + 1, ti, Some(target), None);
+                        stmts.push(Stmt::If {
+                            cond: cond.negate(),
+                            then,
+                            els: Vec::new(),
+                        });
+                        ti
+                    }
+                }
+            }
+        }
+    }
+
+    /// How the block at `index` ends, unless it's a call.
+    fn block_flow(&self, index: usize) -> Option<Flow> {
+        let block = self.block(index);
+        if block.call_return(&self.cfg.disassembly.program).is_some() {
+            return None;
+        }
+        Some(block.last().flow())
+    }
+}
+
+/// Rewrites the loops and branches the structurer produces into their
+/// usual forms, and moves call results into the call.
+fn tidy(stmts: Vec<Stmt>, returns: &BTreeMap<usize, bool>) -> Vec<Stmt> {
+    let mut out: Vec<Stmt> = Vec::with_capacity(stmts.len());
+    for stmt in stmts {
+        let stmt = match stmt {
+            Stmt::If { cond, then, els } => {
+                let (then, els) = (tidy(then, returns), tidy(els, returns));
+                match (then.is_empty(), els.is_empty()) {
+                    // Nothing happens either way once the compare is gone.
+                    (true, true) => continue,
+                    (true, false) => Stmt::If {
+                        cond: cond.negate(),
+                        then: els,
+                        els: then,
+                    },
+                    _ => Stmt::If { cond, then, els },
+                }
+            }
+            Stmt::Loop(body) => loop_form(tidy(body, returns)),
+            Stmt::Assign { dst, value } if value == "out1" => {
+                match out
+                    .iter_mut()
+                    .rev()
+                    .find(|stmt| !matches!(stmt, Stmt::Label(_)))
+                {
+                    Some(Stmt::Call {
+                        target,
+                        result: result @ None,
+                        ..
+                    }) if returns_value(target, returns) => {
+                        *result = Some(dst);
+                        continue;
+                    }
+                    _ => Stmt::Assign { dst, value },
+                }
+            }
+            stmt => stmt,
+        };
+        out.push(stmt);
+    }
+    // What's left in the first slot of the next frame.
+    for stmt in &mut out {
+        if let Stmt::Call {
+            target,
+            result: result @ None,
+            ..
+        } = stmt
+        {
+            if returns_value(target, returns) {
+                *result = Some("out1".to_string());
+            }
+        }
+    }
+    out
+}
+
+fn returns_value(target: &str, returns: &BTreeMap<usize, bool>) -> bool {
+    target
+        .strip_prefix('f')
+        .and_then(|entry| entry.parse().ok())
+        .and_then(|entry| returns.get(&entry))
+        .copied()
+        .unwrap_or(false)
+}
+
+/// `loop { if (c) break; ... }` is `while (!c) { ... }`, and
+/// `loop { ...; if (c) continue; break; }` is `do { ... } while (c)`.
+fn loop_form(mut body: Vec<Stmt>) -> Stmt {
+    // A label left at the top is a goto target, which has to skip the
+    // condition.
+    if let Some(Stmt::If { cond, then, els }) = body.first() {
+        if els.is_empty() && matches!(then.as_slice(), [Stmt::Break]) {
+            let cond = cond.negate();
+            body.remove(0);
+            return Stmt::While { cond, body };
+        }
+    }
+    // A `continue` in a `do` would test the condition first.
+    if let [rest @ .., Stmt::If { then, els, .. }, Stmt::Break] = body.as_slice() {
+        if els.is_empty() && matches!(then.as_slice(), [Stmt::Continue]) && !continues(rest) {
+            body.pop();
+            if let Some(Stmt::If { cond, .. }) = body.pop() {
+                return Stmt::DoWhile { body, cond };
+            }
+        }
+    }
+    Stmt::Loop(body)
+}
+
+/// Whether `stmts` continue the loop they're in.
+fn continues(stmts: &[Stmt]) -> bool {
+    stmts.iter().any(|stmt| match stmt {
+        Stmt::Continue => true,
+        Stmt::If { then, els, .. } => continues(then) || continues(els),
+        _ => false,
+    })
+}
+
+fn gotos(stmts: &[Stmt], targets: &mut BTreeSet<usize>) {
+    for stmt in stmts {
+        match stmt {
+            Stmt::Goto(target) => {
+                targets.insert(*target);
+            }
+            Stmt::If { then, els, .. } => {
+                gotos(then, targets);
+                gotos(els, targets);
+            }
+            Stmt::Loop(body) | Stmt::While { body, .. } | Stmt::DoWhile { body, .. } => {
+                gotos(body, targets)
+            }
+            _ => {}
+        }
+    }
+}
+
+fn drop_labels(stmts: Vec<Stmt>, targets: &BTreeSet<usize>) -> Vec<Stmt> {
+    stmts
+        .into_iter()
+        .filter(|stmt| !matches!(stmt, Stmt::Label(addr) if !targets.contains(addr)))
+        .map(|stmt| match stmt {
+            Stmt::If { cond, then, els } => Stmt::If {
+                cond,
+                then: drop_labels(then, targets),
+                els: drop_labels(els, targets),
+            },
+            Stmt::Loop(body) => Stmt::Loop(drop_labels(body, targets)),
+            Stmt::While { cond, body } => Stmt::While {
+                cond,
+                body: drop_labels(body, targets),
+            },
+            Stmt::DoWhile { body, cond } => Stmt::DoWhile {
+                body: drop_labels(body, targets),
+                cond,
+            },
+            stmt => stmt,
+        })
+        .collect()
+}
+
+fn write_block(f: &mut fmt::Formatter, stmts: &[Stmt], depth: usize) -> fmt::Result {
+    let indent = "    ".repeat(depth);
+    for stmt in stmts {
+        match stmt {
+            Stmt::Label(addr) => writeln!(f, "{}l{}:", "    ".repeat(depth - 1), addr)?,
+            Stmt::Assign { dst, value } => writeln!(f, "{}{} = {}", indent, dst, value)?,
+            Stmt::Call {
+                target,
+                args,
+                result,
+            } => {
+                let result = result
+                    .as_ref()
+                    .map(|result| format!("{} = ", result))
+                    .unwrap_or_default();
+                writeln!(f, "{}{}{}({})", indent, result, target, args.join(", "))?;
+            }
+            Stmt::Line(line) => writeln!(f, "{}{}", indent, line)?,
+            Stmt::Goto(addr) => writeln!(f, "{}goto l{}", indent, addr)?,
+            Stmt::Break => writeln!(f, "{}break", indent)?,
+            Stmt::Continue => writeln!(f, "{}continue", indent)?,
+            Stmt::Return => writeln!(f, "{}return", indent)?,
+            Stmt::If { cond, then, els } => {
+                writeln!(f, "{}if {} {{", indent, cond)?;
+                write_block(f, then, depth + 1)?;
+                let mut els = els.as_slice();
+                // else if, when the else part is a lone if.
+                while let [Stmt::If {
+                    cond,
+                    then,
+                    els: rest,
+                }] = els
+                {
+                    writeln!(f, "{}}} else if {} {{", indent, cond)?;
+                    write_block(f, then, depth + 1)?;
+                    els = rest;
+                }
+                if !els.is_empty() {
+                    writeln!(f, "{}}} else {{", indent)?;
+                    write_block(f, els, depth + 1)?;
+                }
+                writeln!(f, "{}}}", indent)?;
+            }
+            Stmt::Loop(body) => {
+                writeln!(f, "{}loop {{", indent)?;
+                write_block(f, body, depth + 1)?;
+                writeln!(f, "{}}}", indent)?;
+            }
+            Stmt::While { cond, body } => {
+                writeln!(f, "{}while {} {{", indent, cond)?;
+                write_block(f, body, depth + 1)?;
+                writeln!(f, "{}}}", indent)?;
+            }
+            Stmt::DoWhile { body, cond } => {
+                writeln!(f, "{}do {{", indent)?;
+                write_block(f, body, depth + 1)?;
+                writeln!(f, "{}}} while {}", indent, cond)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for Decompilation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, function) in self.functions.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            let params: Vec<_> = (1..=function.args)
+                .map(|slot| format!("arg{}", slot))
+                .collect();
+            writeln!(
+                f,
+                "fn {}({}) {{ ; {}",
+                name(function.entry),
+                params.join(", "),
+                function.entry
+            )?;
+            write_block(f, &function.body, 1)?;
+            writeln!(f, "}}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,380 @@
+use crate::opcode::{decode, Mode, Opcode};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Param {
+    pub mode: Mode,
+    pub value: i64,
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.mode {
+            Mode::Position => write!(f, "[{}]", self.value),
+            Mode::Immediate => write!(f, "#{}", self.value),
+            Mode::Relative if self.value == 0 => write!(f, "rb"),
+            Mode::Relative if self.value < 0 => write!(f, "rb{}", self.value),
+            Mode::Relative => write!(f, "rb+{}", self.value),
+        }
+    }
+}
+
+/// Where control can go after an instruction executes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flow {
+    Next,
+    /// Always jumps to a known address.
+    Jump(usize),
+    /// Either falls through or jumps to a known address.
+    Branch(usize),
+    /// Always jumps to an address only known at runtime.
+    Indirect,
+    /// Either falls through or jumps to an address only known at runtime.
+    IndirectBranch,
+    Halt,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    pub addr: usize,
+    pub opcode: Opcode,
+    pub params: Vec<Param>,
+}
+
+impl Instruction {
+    /// Decodes the instruction at `addr`, returning `None` if the CPU would
+    /// fault on its opcode, on the mode of one of its parameters or on an
+    /// immediate destination.
+    pub fn decode(program: &[i64], addr: usize) -> Option<Self> {
+        let (code, modes) = decode(*program.get(addr)?);
+        let opcode = Opcode::from_code(code)?;
+        let mut params = Vec::with_capacity(opcode.params());
+        for (i, &mode) in modes.iter().enumerate().take(opcode.params()) {
+            let mode = Mode::from_code(mode)?;
+            if mode == Mode::Immediate && opcode.write_param() == Some(i) {
+                return None;
+            }
+            params.push(Param {
+                mode,
+                value: program.get(addr + 1 + i).copied().unwrap_or(0),
+            });
+        }
+        Some(Self {
+            addr,
+            opcode,
+            params,
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        self.opcode.size()
+    }
+
+    pub fn flow(&self) -> Flow {
+        match self.opcode {
+            Opcode::Jnz | Opcode::Jz => {
+                let cond = self.params[0];
+                let target = self.params[1];
+                let taken = match cond.mode {
+                    Mode::Immediate => Some((cond.value != 0) == (self.opcode == Opcode::Jnz)),
+                    _ => None,
+                };
+                let target = match target.mode {
+                    Mode::Immediate => usize::try_from(target.value).ok(),
+                    _ => None,
+                };
+                match (taken, target) {
+                    (Some(false), _) => Flow::Next,
+                    (Some(true), Some(target)) => Flow::Jump(target),
+                    (Some(true), None) => Flow::Indirect,
+                    (None, Some(target)) => Flow::Branch(target),
+                    (None, None) => Flow::IndirectBranch,
+                }
+            }
+            Opcode::Hlt => Flow::Halt,
+            _ => Flow::Next,
+        }
+    }
+
+    /// The constant an `add`/`mul` of two immediates writes, if any.
+    pub fn constant_result(&self) -> Option<i64> {
+        let p = &self.params;
+        match self.opcode {
+            Opcode::Add | Opcode::Mul
+                if p[0].mode == Mode::Immediate && p[1].mode == Mode::Immediate =>
+            {
+                if self.opcode == Opcode::Add {
+                    p[0].value.checked_add(p[1].value)
+                } else {
+                    p[0].value.checked_mul(p[1].value)
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Recognizes the calling idiom `add #ret, #0, rb` followed by an
+/// unconditional jump, where `ret` is the address right after the jump,
+/// and returns `ret`.
+pub fn call_return(program: &[i64], instr: &Instruction) -> Option<usize> {
+    let dst = instr.params.get(2)?;
+    if dst.mode != Mode::Relative || dst.value != 0 {
+        return None;
+    }
+    let ret = usize::try_from(instr.constant_result()?).ok()?;
+    let jump = Instruction::decode(program, instr.addr + instr.size())?;
+    match jump.flow() {
+        Flow::Jump(_) | Flow::Indirect if jump.addr + jump.size() == ret => Some(ret),
+        _ => None,
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.opcode.mnemonic())?;
+        for (i, param) in self.params.iter().enumerate() {
+            write!(f, "{}{}", if i == 0 { " " } else { ", " }, param)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Disassembly {
+    pub program: Vec<i64>,
+    pub instructions: BTreeMap<usize, Instruction>,
+    /// Addresses reached by a jump or a call's return.
+    pub targets: BTreeSet<usize>,
+}
+
+const MIN_STRING_LEN: usize = 4;
+
+fn is_text(value: i64) -> bool {
+    value == '\n' as i64 || (32..127).contains(&value)
+}
+
+/// Most of a string table is words, so require mostly letters and at least
+/// one word break to keep runs of small numbers that happen to be printable
+/// out.
+fn looks_like_text(values: &[i64]) -> bool {
+    let is_break = |v: i64| v == ' ' as i64 || v == '\n' as i64;
+    let wordy = values
+        .iter()
+        .filter(|&&v| is_break(v) || (v as u8 as char).is_ascii_alphabetic())
+        .count();
+    values.len() >= MIN_STRING_LEN
+        && wordy * 5 >= values.len() * 4
+        && values.iter().any(|&v| is_break(v))
+}
+
+/// Follows control flow from pc 0, decoding every reachable instruction.
+/// Jumps through immediate targets are followed, as are return addresses
+/// of the `call_return` idiom; jumps through position or relative operands
+/// are not.
+pub fn disassemble(program: &[i64]) -> Disassembly {
+    disassemble_from(program, &[0])
+}
+
+/// Like `disassemble`, following control flow from each of `roots`.
+pub fn disassemble_from(program: &[i64], roots: &[usize]) -> Disassembly {
+    let mut instructions = BTreeMap::new();
+    let mut targets = BTreeSet::new();
+    let mut todo = roots.to_vec();
+
+    while let Some(addr) = todo.pop() {
+        if instructions.contains_key(&addr) || addr >= program.len() {
+            continue;
+        }
+        let instr = match Instruction::decode(program, addr) {
+            Some(instr) if addr + instr.size() <= program.len() => instr,
+            _ => continue,
+        };
+
+        if let Some(ret) = call_return(program, &instr) {
+            targets.insert(ret);
+            todo.push(ret);
+        }
+
+        match instr.flow() {
+            Flow::Next | Flow::IndirectBranch => todo.push(addr + instr.size()),
+            Flow::Branch(target) => {
+                targets.insert(target);
+                todo.push(target);
+                todo.push(addr + instr.size());
+            }
+            Flow::Jump(target) => {
+                targets.insert(target);
+                todo.push(target);
+            }
+            Flow::Indirect | Flow::Halt => {}
+        }
+
+        instructions.insert(addr, instr);
+    }
+
+    // A jump into the middle of another instruction can't be listed as both;
+    // keep the first one and let the other fall into the data.
+    let mut end = 0;
+    instructions.retain(|&addr, instr| {
+        if addr < end {
+            return false;
+        }
+        end = addr + instr.size();
+        true
+    });
+
+    Disassembly {
+        program: program.to_vec(),
+        instructions,
+        targets,
+    }
+}
+
+impl Disassembly {
+    /// Returns the addresses not covered by any reachable instruction,
+    /// grouped into contiguous ranges.
+    pub fn data_ranges(&self) -> Vec<(usize, usize)> {
+        let mut ranges = Vec::new();
+        let mut addr = 0;
+        let mut start = None;
+        while addr < self.program.len() {
+            if let Some(instr) = self.instructions.get(&addr) {
+                if let Some(s) = start.take() {
+                    ranges.push((s, addr));
+                }
+                addr += instr.size();
+            } else {
+                start.get_or_insert(addr);
+                addr += 1;
+            }
+        }
+        if let Some(s) = start {
+            ranges.push((s, self.program.len()));
+        }
+        ranges
+    }
+
+    /// Returns the runs of printable ASCII inside data ranges that are long
+    /// enough to be a string table.
+    pub fn strings(&self) -> Vec<(usize, usize)> {
+        let mut strings = Vec::new();
+        for (start, end) in self.data_ranges() {
+            let mut run = start;
+            for addr in start..=end {
+                if addr < end && is_text(self.program[addr]) {
+                    continue;
+                }
+                if looks_like_text(&self.program[run..addr]) {
+                    strings.push((run, addr));
+                }
+                run = addr + 1;
+            }
+        }
+        strings
+    }
+
+    fn label(&self, addr: usize) -> Option<String> {
+        if self.targets.contains(&addr) && self.instructions.contains_key(&addr) {
+            Some(format!("l{}", addr))
+        } else {
+            None
+        }
+    }
+
+    /// Formats `instr`, naming its jump target by label when there is one.
+    pub(crate) fn instruction_text(&self, instr: &Instruction) -> String {
+        let target = match instr.flow() {
+            Flow::Jump(target) | Flow::Branch(target) => self.label(target),
+            _ => None,
+        };
+        match target {
+            Some(label) => format!(
+                "{} {}, #{}",
+                instr.opcode.mnemonic(),
+                instr.params[0],
+                label
+            ),
+            None => instr.to_string(),
+        }
+    }
+
+    fn write_line(
+        f: &mut fmt::Formatter,
+        label: Option<String>,
+        text: &str,
+        addr: usize,
+        note: &str,
+    ) -> fmt::Result {
+        let label = label.map(|l| l + ":").unwrap_or_default();
+        let line = format!("{:<8}{}", label, text);
+        writeln!(f, "{:<39} ; {}{}", line, addr, note)
+    }
+
+    fn write_data(&self, f: &mut fmt::Formatter, start: usize, end: usize) -> fmt::Result {
+        for chunk_start in (start..end).step_by(8) {
+            let chunk = &self.program[chunk_start..end.min(chunk_start + 8)];
+            let values: Vec<_> = chunk.iter().map(|v| v.to_string()).collect();
+            Self::write_line(
+                f,
+                None,
+                &format!(".data {}", values.join(", ")),
+                chunk_start,
+                "",
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_string(&self, f: &mut fmt::Formatter, start: usize, end: usize) -> fmt::Result {
+        let text: String = self.program[start..end]
+            .iter()
+            .map(|&c| match c as u8 as char {
+                '\n' => "\\n".to_string(),
+                '"' => "\\\"".to_string(),
+                '\\' => "\\\\".to_string(),
+                c => c.to_string(),
+            })
+            .collect();
+        Self::write_line(f, None, &format!(".data \"{}\"", text), start, " string")
+    }
+}
+
+/// Prints the listing in a form `intcode::asm::assemble` accepts, with
+/// each line's address in a trailing comment.
+impl fmt::Display for Disassembly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let strings = self.strings();
+        let mut addr = 0;
+        while addr < self.program.len() {
+            if let Some(instr) = self.instructions.get(&addr) {
+                let note = match instr.flow() {
+                    Flow::Indirect | Flow::IndirectBranch => " indirect",
+                    _ => "",
+                };
+                let text = self.instruction_text(instr);
+                Self::write_line(f, self.label(addr), &text, addr, note)?;
+                addr += instr.size();
+                continue;
+            }
+
+            let end = (addr..self.program.len())
+                .find(|a| self.instructions.contains_key(a))
+                .unwrap_or(self.program.len());
+            let mut a = addr;
+            for &(s, e) in strings.iter().filter(|&&(s, _)| s >= addr && s < end) {
+                if a < s {
+                    self.write_data(f, a, s)?;
+                }
+                self.write_string(f, s, e)?;
+                a = e;
+            }
+            if a < end {
+                self.write_data(f, a, end)?;
+            }
+            addr = end;
+        }
+        Ok(())
+    }
+}
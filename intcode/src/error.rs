@@ -0,0 +1,64 @@
+use crate::opcode::decode;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    InvalidOpcode,
+    InvalidMode(i64),
+    ImmediateStore,
+    /// A computed address or jump target that is negative or does not fit
+    /// in the address space, or a store past what the memory backend can
+    /// hold. When adding the relative base itself
+    /// overflows, this carries the operand that was added to it.
+    InvalidAddress(i64),
+    /// An `add` or `mul` whose result doesn't fit, with its two operands.
+    /// Operands from cells wider than `i64` are clamped to its range.
+    Overflow(i64, i64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IntcodeError {
+    pub pc: usize,
+    pub instruction: i64,
+    pub opcode: i64,
+    pub modes: [i64; 3],
+    pub kind: ErrorKind,
+}
+
+impl IntcodeError {
+    pub fn new(pc: usize, instruction: i64, kind: ErrorKind) -> Self {
+        let (opcode, modes) = decode(instruction);
+        Self {
+            pc,
+            instruction,
+            opcode,
+            modes,
+            kind,
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::InvalidOpcode => write!(f, "invalid opcode"),
+            ErrorKind::InvalidMode(mode) => write!(f, "invalid mode: {}", mode),
+            ErrorKind::ImmediateStore => write!(f, "store instruction with immediate mode"),
+            ErrorKind::InvalidAddress(addr) => write!(f, "invalid address: {}", addr),
+            ErrorKind::Overflow(a, b) => write!(f, "arithmetic overflow on {} and {}", a, b),
+        }
+    }
+}
+
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at pc {} (instruction {}, opcode {}, modes {:?})",
+            self.kind, self.pc, self.instruction, self.opcode, self.modes
+        )
+    }
+}
+
+impl Error for IntcodeError {}
@@ -0,0 +1,757 @@
+//! Differential fuzzing of the CPU backends.
+//!
+//! `Program::generate` builds a random program out of arithmetic,
+//! comparisons, input and output over a handful of variables, relative
+//! operands into a scratch area, forward conditional jumps, counted loops
+//! and stores that patch immediate operands of other instructions. Loop
+//! counters are only ever written by their own loop, and nothing jumps
+//! backwards otherwise, so every program terminates.
+//!
+//! `check` runs a program on every `Backend` and compares output, final
+//! memory and how the run ended against the cached interpreter. `minimize`
+//! then shrinks a diverging program, whose `source` is assembler input
+//! that can be kept as a regression test.
+
+use crate::cell::Cell;
+use crate::cpu::{Cpu, Machine, Status};
+use crate::error::{ErrorKind, IntcodeError};
+use crate::memory::{Dense, Hybrid, Memory, Sparse};
+use crate::opcode::Opcode;
+use crate::promote::Promoting;
+use num_bigint::BigInt;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{self, Write};
+
+/// splitmix64, so a seed always generates the same program.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// A value in `lo..=hi`.
+    fn range(&mut self, lo: i64, hi: i64) -> i64 {
+        lo + (self.next() % (hi - lo + 1) as u64) as i64
+    }
+
+    fn one_in(&mut self, n: usize) -> bool {
+        self.below(n) == 0
+    }
+}
+
+/// Relative operands stay within this distance of the relative base.
+const REL_RANGE: i64 = 8;
+
+/// How far into the scratch area the relative base starts, leaving room for
+/// `arb`s to move it either way.
+const SCRATCH_BASE: i64 = 64;
+
+#[derive(Clone, Debug)]
+enum Src {
+    Imm(i64),
+    Var(usize),
+    Rel(i64),
+}
+
+#[derive(Clone, Debug)]
+enum Dst {
+    Var(usize),
+    Rel(i64),
+    /// Word `word` of the op with id `op`, an immediate operand.
+    Code {
+        op: usize,
+        word: usize,
+    },
+}
+
+#[derive(Clone, Debug)]
+enum Op {
+    /// `add`, `mul`, `lt` or `eq`.
+    Arith {
+        id: usize,
+        opcode: Opcode,
+        a: Src,
+        b: Src,
+        dst: Dst,
+    },
+    In(Dst),
+    Out {
+        id: usize,
+        src: Src,
+    },
+    /// Only ever at the top level, so the relative base can't drift far.
+    Arb(i64),
+    /// `jz` or `jnz` past the next `len` ops.
+    Skip {
+        opcode: Opcode,
+        cond: Src,
+        len: usize,
+    },
+    /// `body` run `count` times, counting down in a variable of its own.
+    Loop {
+        id: usize,
+        count: i64,
+        body: Vec<Op>,
+    },
+}
+
+/// A generated program and the input it runs on.
+#[derive(Clone, Debug)]
+pub struct Program {
+    vars: Vec<i64>,
+    ops: Vec<Op>,
+    pub input: Vec<i64>,
+}
+
+struct Generator {
+    rng: Rng,
+    vars: usize,
+    next_id: usize,
+    /// Ops with an immediate operand, as patch targets.
+    patchable: Vec<(usize, usize)>,
+}
+
+impl Generator {
+    fn id(&mut self) -> usize {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn src(&mut self) -> Src {
+        match self.rng.below(4) {
+            0 => Src::Imm(self.rng.range(-20, 20)),
+            1 => Src::Rel(self.rng.range(-REL_RANGE, REL_RANGE)),
+            _ => Src::Var(self.rng.below(self.vars)),
+        }
+    }
+
+    fn dst(&mut self) -> Dst {
+        if !self.patchable.is_empty() && self.rng.one_in(8) {
+            let (op, word) = self.patchable[self.rng.below(self.patchable.len())];
+            return Dst::Code { op, word };
+        }
+        match self.rng.below(4) {
+            0 => Dst::Rel(self.rng.range(-REL_RANGE, REL_RANGE)),
+            _ => Dst::Var(self.rng.below(self.vars)),
+        }
+    }
+
+    fn ops(&mut self, len: usize, depth: usize) -> Vec<Op> {
+        let mut ops = Vec::new();
+        while ops.len() < len {
+            let op = match self.rng.below(16) {
+                0..=6 => {
+                    let id = self.id();
+                    let opcode =
+                        [Opcode::Add, Opcode::Mul, Opcode::Lt, Opcode::Eq][self.rng.below(4)];
+                    let (a, b, dst) = (self.src(), self.src(), self.dst());
+                    for (word, src) in [(1, &a), (2, &b)].iter() {
+                        if let Src::Imm(_) = src {
+                            self.patchable.push((id, *word));
+                        }
+                    }
+                    Op::Arith {
+                        id,
+                        opcode,
+                        a,
+                        b,
+                        dst,
+                    }
+                }
+                7 => Op::In(self.dst()),
+                8 | 9 => {
+                    let id = self.id();
+                    let src = self.src();
+                    if let Src::Imm(_) = src {
+                        self.patchable.push((id, 1));
+                    }
+                    Op::Out { id, src }
+                }
+                10 if depth == 0 => Op::Arb(self.rng.range(-4, 4)),
+                11 | 12 => Op::Skip {
+                    opcode: [Opcode::Jz, Opcode::Jnz][self.rng.below(2)],
+                    cond: self.src(),
+                    len: self.rng.range(1, 4) as usize,
+                },
+                13 if depth < 2 => {
+                    let id = self.id();
+                    let len = self.rng.range(1, 6) as usize;
+                    Op::Loop {
+                        id,
+                        count: self.rng.range(1, 8),
+                        body: self.ops(len, depth + 1),
+                    }
+                }
+                _ => continue,
+            };
+            ops.push(op);
+        }
+        ops
+    }
+}
+
+impl Program {
+    /// The program for `seed`.
+    pub fn generate(seed: u64) -> Self {
+        let mut gen = Generator {
+            rng: Rng(seed),
+            vars: 0,
+            next_id: 0,
+            patchable: Vec::new(),
+        };
+        gen.vars = gen.rng.range(1, 6) as usize;
+        let vars = (0..gen.vars).map(|_| gen.rng.range(-10, 10)).collect();
+        let len = gen.rng.range(1, 30) as usize;
+        let ops = gen.ops(len, 0);
+        let input = (0..gen.rng.below(4))
+            .map(|_| gen.rng.range(-10, 10))
+            .collect();
+        Program { vars, ops, input }
+    }
+
+    /// Assembler source for the program, with its input in a comment.
+    pub fn source(&self) -> String {
+        let mut immediates = HashSet::new();
+        collect_immediates(&self.ops, &mut immediates);
+
+        let mut out = String::new();
+        let input: Vec<_> = self.input.iter().map(i64::to_string).collect();
+        let _ = writeln!(out, "; input: {}", input.join(","));
+        let _ = writeln!(out, "        arb #scratch+{}", SCRATCH_BASE);
+        let mut labels = 0;
+        write_ops(&mut out, &self.ops, &immediates, &mut labels);
+        let _ = writeln!(out, "        hlt");
+        for (i, value) in self.vars.iter().enumerate() {
+            let _ = writeln!(out, "v{}:     .data {}", i, value);
+        }
+        let mut counters = Vec::new();
+        collect_loops(&self.ops, &mut counters);
+        for id in counters {
+            let _ = writeln!(out, "c{}:     .data 0", id);
+        }
+        let _ = writeln!(out, "scratch: .data 0");
+        out
+    }
+
+    /// The assembled program.
+    pub fn image(&self) -> Vec<i64> {
+        crate::asm::assemble(&self.source()).expect("generated source assembles")
+    }
+}
+
+fn collect_immediates(ops: &[Op], out: &mut HashSet<(usize, usize)>) {
+    for op in ops {
+        match op {
+            Op::Arith { id, a, b, .. } => {
+                for (word, src) in [(1, a), (2, b)].iter() {
+                    if let Src::Imm(_) = src {
+                        out.insert((*id, *word));
+                    }
+                }
+            }
+            Op::Out {
+                id,
+                src: Src::Imm(_),
+            } => {
+                out.insert((*id, 1));
+            }
+            Op::Loop { body, .. } => collect_immediates(body, out),
+            _ => {}
+        }
+    }
+}
+
+fn collect_loops(ops: &[Op], out: &mut Vec<usize>) {
+    for op in ops {
+        if let Op::Loop { id, body, .. } = op {
+            out.push(*id);
+            collect_loops(body, out);
+        }
+    }
+}
+
+impl fmt::Display for Src {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Src::Imm(value) => write!(f, "#{}", value),
+            Src::Var(var) => write!(f, "[v{}]", var),
+            Src::Rel(offset) => write_rel(f, offset),
+        }
+    }
+}
+
+fn write_rel(f: &mut fmt::Formatter, offset: i64) -> fmt::Result {
+    match offset {
+        0 => write!(f, "rb"),
+        _ if offset < 0 => write!(f, "rb{}", offset),
+        _ => write!(f, "rb+{}", offset),
+    }
+}
+
+/// A destination as assembler text. A patch whose target has been
+/// minimized away writes to the first variable instead.
+fn dst_text(dst: &Dst, immediates: &HashSet<(usize, usize)>) -> String {
+    match *dst {
+        Dst::Var(var) => format!("[v{}]", var),
+        Dst::Rel(offset) => Src::Rel(offset).to_string(),
+        Dst::Code { op, word } if immediates.contains(&(op, word)) => {
+            format!("[o{}+{}]", op, word)
+        }
+        Dst::Code { .. } => "[v0]".to_string(),
+    }
+}
+
+fn write_ops(
+    out: &mut String,
+    ops: &[Op],
+    immediates: &HashSet<(usize, usize)>,
+    labels: &mut usize,
+) {
+    // Labels for the ends of skips, by the index of the op they precede.
+    let mut ends: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (i, op) in ops.iter().enumerate() {
+        for label in ends.remove(&i).unwrap_or_default() {
+            let _ = writeln!(out, "s{}:", label);
+        }
+        let label = |id: usize| {
+            if immediates.iter().any(|&(op, _)| op == id) {
+                format!("o{}:", id)
+            } else {
+                String::new()
+            }
+        };
+        let _ = match op {
+            Op::Arith {
+                id,
+                opcode,
+                a,
+                b,
+                dst,
+            } => writeln!(
+                out,
+                "{:<8}{} {}, {}, {}",
+                label(*id),
+                opcode.mnemonic(),
+                a,
+                b,
+                dst_text(dst, immediates)
+            ),
+            Op::In(dst) => writeln!(out, "        in {}", dst_text(dst, immediates)),
+            Op::Out { id, src } => writeln!(out, "{:<8}out {}", label(*id), src),
+            Op::Arb(value) => writeln!(out, "        arb #{}", value),
+            Op::Skip { opcode, cond, len } => {
+                *labels += 1;
+                ends.entry((i + 1 + len).min(ops.len()))
+                    .or_default()
+                    .push(*labels);
+                writeln!(out, "        {} {}, #s{}", opcode.mnemonic(), cond, labels)
+            }
+            Op::Loop { id, count, body } => {
+                let _ = writeln!(out, "        add #{}, #0, [c{}]", count, id);
+                let _ = writeln!(out, "l{}:", id);
+                write_ops(out, body, immediates, labels);
+                let _ = writeln!(out, "        add [c{}], #-1, [c{}]", id, id);
+                writeln!(out, "        jnz [c{}], #l{}", id, id)
+            }
+        };
+    }
+    for label in ends.remove(&ops.len()).unwrap_or_default() {
+        let _ = writeln!(out, "s{}:", label);
+    }
+}
+
+/// A way of running a program that should behave like every other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    Cached,
+    Uncached,
+    Sparse,
+    Hybrid,
+    /// The interpreter with code-write detection on, which bypasses the
+    /// decode cache.
+    CodeWrites,
+    Jit,
+    I128,
+    BigInt,
+    Promote,
+}
+
+pub const BACKENDS: [Backend; 9] = [
+    Backend::Cached,
+    Backend::Uncached,
+    Backend::Sparse,
+    Backend::Hybrid,
+    Backend::CodeWrites,
+    Backend::Jit,
+    Backend::I128,
+    Backend::BigInt,
+    Backend::Promote,
+];
+
+impl Backend {
+    /// Whether the backend computes with cells wider than `i64`.
+    pub fn is_wide(self) -> bool {
+        matches!(self, Backend::I128 | Backend::BigInt | Backend::Promote)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Cached => "cached",
+            Backend::Uncached => "uncached",
+            Backend::Sparse => "sparse",
+            Backend::Hybrid => "hybrid",
+            Backend::CodeWrites => "code-writes",
+            Backend::Jit => "jit",
+            Backend::I128 => "i128",
+            Backend::BigInt => "bigint",
+            Backend::Promote => "promote",
+        }
+    }
+
+    /// Runs `program` on `input` until it halts, faults or wants more
+    /// input. `None` if the backend isn't built in.
+    pub fn run(self, program: &[i64], input: &[i64]) -> Option<Outcome> {
+        Some(match self {
+            Backend::Cached => run_cpu(Cpu::new(program), input),
+            Backend::Uncached => {
+                let mut cpu = Uncached(Cpu::new(program));
+                let (outputs, end) = drive(&mut cpu, input);
+                Outcome::new(outputs, cpu.0.mem().cells(), end)
+            }
+            Backend::Sparse => run_cpu(Cpu::with_memory(Sparse::new(), program), input),
+            // A low limit, so that the scratch area is sparse.
+            Backend::Hybrid => run_cpu(Cpu::with_memory(Hybrid::with_limit(16), program), input),
+            Backend::CodeWrites => {
+                let mut cpu = Cpu::new(program);
+                cpu.detect_code_writes(true);
+                run_cpu(cpu, input)
+            }
+            #[cfg(all(feature = "jit", target_arch = "x86_64", target_os = "linux"))]
+            Backend::Jit => {
+                let mut jit = crate::jit::Jit::new(program);
+                let (outputs, end) = drive(&mut jit, input);
+                Outcome::new(outputs, jit.cpu().mem().cells(), end)
+            }
+            #[cfg(not(all(feature = "jit", target_arch = "x86_64", target_os = "linux")))]
+            Backend::Jit => return None,
+            Backend::I128 => run_cpu(
+                Cpu::with_memory(Dense::<i128>::new(), &widen(program)),
+                input,
+            ),
+            Backend::BigInt => run_cpu(
+                Cpu::with_memory(Dense::<BigInt>::new(), &widen(program)),
+                input,
+            ),
+            Backend::Promote => {
+                let mut cpu = Promoting::new(program);
+                let (outputs, end) = drive(&mut cpu, input);
+                Outcome::new(outputs, cpu.cells(), end)
+            }
+        })
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+fn widen<C: Cell>(program: &[i64]) -> Vec<C> {
+    program
+        .iter()
+        .map(|&value| C::from_i64(value).expect("i64 fits a wider cell"))
+        .collect()
+}
+
+/// The interpreter without its decode cache.
+struct Uncached(Cpu);
+
+impl Machine for Uncached {
+    fn push_input(&mut self, value: i64) {
+        self.0.push_input(value);
+    }
+
+    fn run(&mut self) -> Result<Status, IntcodeError> {
+        self.0.run_uncached()
+    }
+}
+
+fn run_cpu<M: Memory>(mut cpu: Cpu<M>, input: &[i64]) -> Outcome {
+    let (outputs, end) = drive(&mut cpu, input);
+    Outcome::new(outputs, cpu.mem().cells(), end)
+}
+
+fn drive<C: Cell>(machine: &mut impl Machine<C>, input: &[i64]) -> (Vec<C>, End) {
+    let mut input = input.iter();
+    let mut outputs = Vec::new();
+    let end = loop {
+        match machine.run() {
+            Ok(Status::Output(value)) => outputs.push(value),
+            Ok(Status::NeedsInput) => match input.next().and_then(|&value| C::from_i64(value)) {
+                Some(value) => machine.push_input(value),
+                None => break End::NeedsInput,
+            },
+            Ok(Status::Halted) => break End::Halted,
+            Err(e) => break End::Fault(e),
+        }
+    };
+    (outputs, end)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum End {
+    Halted,
+    /// The program wanted more input than it was given.
+    NeedsInput,
+    Fault(IntcodeError),
+}
+
+impl fmt::Display for End {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            End::Halted => write!(f, "halted"),
+            End::NeedsInput => write!(f, "waiting for input"),
+            End::Fault(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// How a run went. Values are kept as text so that backends with
+/// different cell types compare directly.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Outcome {
+    pub outputs: Vec<String>,
+    /// Every non-zero memory cell, in address order.
+    pub mem: Vec<(usize, String)>,
+    pub end: End,
+}
+
+impl Outcome {
+    fn new<C: Cell>(outputs: Vec<C>, mem: Vec<(usize, C)>, end: End) -> Self {
+        Self {
+            outputs: outputs.iter().map(C::to_string).collect(),
+            mem: mem
+                .into_iter()
+                .map(|(addr, value)| (addr, value.to_string()))
+                .collect(),
+            end,
+        }
+    }
+
+    fn overflowed(&self) -> bool {
+        matches!(
+            self.end,
+            End::Fault(IntcodeError {
+                kind: ErrorKind::Overflow(..),
+                ..
+            })
+        )
+    }
+}
+
+/// A backend that didn't do what the cached interpreter did.
+#[derive(Clone, Debug)]
+pub struct Divergence {
+    pub backend: Backend,
+    pub expected: Outcome,
+    pub found: Outcome,
+}
+
+/// Describes the first difference: in output, then in how the run ended,
+/// then in memory.
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} differs from cached: ", self.backend)?;
+        let (expected, found) = (&self.expected, &self.found);
+        let none = "nothing".to_string();
+        if let Some(i) = (0..expected.outputs.len().max(found.outputs.len()))
+            .find(|&i| expected.outputs.get(i) != found.outputs.get(i))
+        {
+            return write!(
+                f,
+                "output {} is {}, expected {}",
+                i,
+                found.outputs.get(i).unwrap_or(&none),
+                expected.outputs.get(i).unwrap_or(&none)
+            );
+        }
+        if expected.end != found.end {
+            return write!(f, "{}, expected {}", found.end, expected.end);
+        }
+        let zero = "0".to_string();
+        let mut cells: BTreeMap<usize, (&String, &String)> = BTreeMap::new();
+        for (addr, value) in &expected.mem {
+            cells.entry(*addr).or_insert((&zero, &zero)).0 = value;
+        }
+        for (addr, value) in &found.mem {
+            cells.entry(*addr).or_insert((&zero, &zero)).1 = value;
+        }
+        match cells.iter().find(|(_, (a, b))| a != b) {
+            Some((addr, (expected, found))) => {
+                write!(f, "memory at {} is {}, expected {}", addr, found, expected)
+            }
+            None => write!(f, "no difference"),
+        }
+    }
+}
+
+/// Runs `program` on every backend; each must match the cached
+/// interpreter. A program that overflows an `i64` is left out on the wide
+/// backends, which keep computing where the others trap, sometimes with
+/// numbers too big to finish with.
+pub fn check(program: &[i64], input: &[i64]) -> Result<(), Box<Divergence>> {
+    let expected = Backend::Cached.run(program, input).expect("always built");
+    for &backend in &BACKENDS[1..] {
+        if backend.is_wide() && expected.overflowed() {
+            continue;
+        }
+        match backend.run(program, input) {
+            Some(found) if found != expected => {
+                return Err(Box::new(Divergence {
+                    backend,
+                    expected,
+                    found,
+                }))
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Shrinks `program` while `fails` still holds for it: drops ops, inputs
+/// and variables' initial values, unrolls loops into a single pass and
+/// simplifies immediates.
+pub fn minimize(program: &Program, fails: impl Fn(&Program) -> bool) -> Program {
+    let mut best = program.clone();
+    loop {
+        let mut improved = false;
+        for candidate in shrink(&best) {
+            if fails(&candidate) {
+                best = candidate;
+                improved = true;
+                break;
+            }
+        }
+        if !improved {
+            return best;
+        }
+    }
+}
+
+/// Every program one simplification away from `program`.
+fn shrink(program: &Program) -> Vec<Program> {
+    let mut candidates = Vec::new();
+    for ops in shrink_ops(&program.ops) {
+        candidates.push(Program {
+            ops,
+            ..program.clone()
+        });
+    }
+    for i in 0..program.input.len() {
+        let mut input = program.input.clone();
+        input.remove(i);
+        candidates.push(Program {
+            input,
+            ..program.clone()
+        });
+    }
+    for i in 0..program.vars.len() {
+        if program.vars[i] != 0 {
+            let mut vars = program.vars.clone();
+            vars[i] = 0;
+            candidates.push(Program {
+                vars,
+                ..program.clone()
+            });
+        }
+    }
+    candidates
+}
+
+fn shrink_ops(ops: &[Op]) -> Vec<Vec<Op>> {
+    let mut candidates = Vec::new();
+    for i in 0..ops.len() {
+        let mut without = ops.to_vec();
+        without.remove(i);
+        candidates.push(without);
+    }
+    for (i, op) in ops.iter().enumerate() {
+        let mut replace = |op: Op| {
+            let mut ops = ops.to_vec();
+            ops[i] = op;
+            candidates.push(ops);
+        };
+        match op {
+            Op::Loop { id, count, body } => {
+                for fewer in [1, count - 1].iter().filter(|&&n| n >= 1 && n < *count) {
+                    replace(Op::Loop {
+                        id: *id,
+                        count: *fewer,
+                        body: body.clone(),
+                    });
+                }
+                for body in shrink_ops(body) {
+                    replace(Op::Loop {
+                        id: *id,
+                        count: *count,
+                        body,
+                    });
+                }
+            }
+            Op::Arith {
+                id,
+                opcode,
+                a,
+                b,
+                dst,
+            } => {
+                for (a, b) in [(simpler(a), Some(b.clone())), (Some(a.clone()), simpler(b))].iter()
+                {
+                    if let (Some(a), Some(b)) = (a, b) {
+                        replace(Op::Arith {
+                            id: *id,
+                            opcode: *opcode,
+                            a: a.clone(),
+                            b: b.clone(),
+                            dst: dst.clone(),
+                        });
+                    }
+                }
+            }
+            Op::Out { id, src } => {
+                if let Some(src) = simpler(src) {
+                    replace(Op::Out { id: *id, src });
+                }
+            }
+            _ => {}
+        }
+    }
+    // A loop that only needs one pass can lose its loop.
+    for (i, op) in ops.iter().enumerate() {
+        if let Op::Loop { count: 1, body, .. } = op {
+            let mut ops = ops.to_vec();
+            ops.splice(i..=i, body.iter().cloned());
+            candidates.push(ops);
+        }
+    }
+    candidates
+}
+
+/// A smaller immediate, if there is one.
+fn simpler(src: &Src) -> Option<Src> {
+    match *src {
+        Src::Imm(value) if value != 0 => Some(Src::Imm(value / 2)),
+        _ => None,
+    }
+}
@@ -0,0 +1,185 @@
+//! Where a machine's input comes from and its output goes.
+//!
+//! `Machine::run_io` pulls from an `InputSource` whenever the program
+//! wants input and pushes every output to an `OutputSink`, so the glue
+//! between a CPU and the rest of a puzzle is one of the stock types here:
+//!
+//! - `VecDeque` is both a source and a sink, `Vec` a sink.
+//! - `Option` supplies its value once.
+//! - `Iter` wraps an iterator, `FromFn` a closure.
+//! - `mpsc::Receiver` blocks for its next value; `Sender` and `SyncSender`
+//!   send.
+//! - `AsciiReader` and `AsciiWriter` translate lines of text, for the
+//!   puzzles that talk ASCII.
+
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::io::{self, BufRead, StdinLock, Stdout, Write};
+use std::sync::mpsc::{Receiver, Sender, SyncSender};
+
+pub trait InputSource<C = i64> {
+    /// The next value, or `None` if there is none for now. The machine
+    /// then stops with `NeedsInput`.
+    fn next_input(&mut self) -> Option<C>;
+}
+
+pub trait OutputSink<C = i64> {
+    fn output(&mut self, value: C);
+}
+
+impl<C, T: InputSource<C> + ?Sized> InputSource<C> for &mut T {
+    fn next_input(&mut self) -> Option<C> {
+        (**self).next_input()
+    }
+}
+
+impl<C, T: OutputSink<C> + ?Sized> OutputSink<C> for &mut T {
+    fn output(&mut self, value: C) {
+        (**self).output(value)
+    }
+}
+
+impl<C> InputSource<C> for VecDeque<C> {
+    fn next_input(&mut self) -> Option<C> {
+        self.pop_front()
+    }
+}
+
+impl<C> OutputSink<C> for VecDeque<C> {
+    fn output(&mut self, value: C) {
+        self.push_back(value);
+    }
+}
+
+impl<C> OutputSink<C> for Vec<C> {
+    fn output(&mut self, value: C) {
+        self.push(value);
+    }
+}
+
+impl<C> InputSource<C> for Option<C> {
+    fn next_input(&mut self) -> Option<C> {
+        self.take()
+    }
+}
+
+/// Feeds the values of an iterator.
+pub struct Iter<I>(pub I);
+
+impl<C, I: Iterator<Item = C>> InputSource<C> for Iter<I> {
+    fn next_input(&mut self) -> Option<C> {
+        self.0.next()
+    }
+}
+
+/// A closure as a source, `FnMut() -> Option<C>`, or as a sink,
+/// `FnMut(C)`.
+pub struct FromFn<F>(pub F);
+
+impl<C, F: FnMut() -> Option<C>> InputSource<C> for FromFn<F> {
+    fn next_input(&mut self) -> Option<C> {
+        (self.0)()
+    }
+}
+
+impl<C, F: FnMut(C)> OutputSink<C> for FromFn<F> {
+    fn output(&mut self, value: C) {
+        (self.0)(value)
+    }
+}
+
+/// Blocks until a value arrives. Once every sender is gone there is no
+/// more input.
+impl<C> InputSource<C> for Receiver<C> {
+    fn next_input(&mut self) -> Option<C> {
+        self.recv().ok()
+    }
+}
+
+/// Output sent after the receiver is gone is dropped.
+impl<C> OutputSink<C> for Sender<C> {
+    fn output(&mut self, value: C) {
+        let _ = self.send(value);
+    }
+}
+
+impl<C> OutputSink<C> for SyncSender<C> {
+    fn output(&mut self, value: C) {
+        let _ = self.send(value);
+    }
+}
+
+/// Reads lines of text and feeds them as ASCII codes, newline included.
+pub struct AsciiReader<R> {
+    reader: R,
+    line: VecDeque<u8>,
+}
+
+impl<R: BufRead> AsciiReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: VecDeque::new(),
+        }
+    }
+}
+
+impl AsciiReader<StdinLock<'static>> {
+    pub fn stdin() -> Self {
+        Self::new(io::stdin().lock())
+    }
+}
+
+/// Reads a new line only when the last one is used up; at end of input or
+/// on a read error there is no more input.
+impl<R: BufRead> InputSource<i64> for AsciiReader<R> {
+    fn next_input(&mut self) -> Option<i64> {
+        if self.line.is_empty() {
+            let mut line = String::new();
+            match self.reader.read_line(&mut line) {
+                Ok(n) if n > 0 => {}
+                _ => return None,
+            }
+            if !line.ends_with('\n') {
+                line.push('\n');
+            }
+            self.line.extend(line.bytes());
+        }
+        self.line.pop_front().map(i64::from)
+    }
+}
+
+/// Writes ASCII codes as text. Values outside ASCII, such as a puzzle's
+/// final answer, are written as numbers on a line of their own.
+pub struct AsciiWriter<W> {
+    writer: W,
+}
+
+impl<W: Write> AsciiWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl AsciiWriter<Stdout> {
+    pub fn stdout() -> Self {
+        Self::new(io::stdout())
+    }
+}
+
+/// Write errors are ignored.
+impl<W: Write> OutputSink<i64> for AsciiWriter<W> {
+    fn output(&mut self, value: i64) {
+        let _ = match u8::try_from(value) {
+            Ok(c) if c.is_ascii() => self.writer.write_all(&[c]),
+            _ => writeln!(self.writer, "{}", value),
+        };
+        if value == i64::from(b'\n') {
+            let _ = self.writer.flush();
+        }
+    }
+}
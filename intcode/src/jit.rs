@@ -0,0 +1,856 @@
+//! A basic-block JIT for x86-64 Linux.
+//!
+//! A block is a run of `add`, `mul`, `lt`, `eq` and `arb` instructions,
+//! optionally ended by a `jnz` or `jz`. It is compiled to native code in
+//! an mmap'd region and jumps straight on to the block at the next pc
+//! when there is one, returning to the Rust driver otherwise. Inputs,
+//! outputs, halts and anything the compiled code can't handle on its own
+//! (addresses past the end of memory, a store into compiled code, a
+//! fault) are left to the interpreter, one instruction at a time.
+//!
+//! Clones of a `Jit` share compiled blocks. Each block remembers the
+//! words it was compiled from and how long memory must be for its
+//! unchecked constant addresses, and a `Jit` only runs it once its own
+//! memory matches. The blocks that passed that check are kept in a table
+//! per `Jit`, with a bitmap of the words they cover: compiled stores into
+//! a covered word take the slow path, and the interpreter's store retires
+//! the blocks it changes. Operands the program patches, like the address
+//! of an array access, are read from memory when the block runs instead,
+//! so patching them doesn't retire anything.
+//!
+//! An `add` or `mul` that overflows takes the slow path, so the
+//! interpreter applies the CPU's overflow policy to it.
+
+use crate::cache::DecodeCache;
+use crate::cpu::{Cpu, Machine, Status};
+use crate::disasm::{self, Instruction, Param};
+use crate::error::IntcodeError;
+use crate::opcode::{decode, Mode, Opcode};
+use crate::overflow::Overflow;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::ffi::c_void;
+use std::ptr;
+use std::rc::Rc;
+
+extern "C" {
+    fn mmap(addr: *mut c_void, len: usize, prot: i32, flags: i32, fd: i32, off: i64)
+        -> *mut c_void;
+    fn munmap(addr: *mut c_void, len: usize) -> i32;
+}
+
+const PROT_READ: i32 = 1;
+const PROT_WRITE: i32 = 2;
+const PROT_EXEC: i32 = 4;
+const MAP_PRIVATE: i32 = 2;
+const MAP_ANONYMOUS: i32 = 0x20;
+
+/// Size of the executable region. When it fills up every block is thrown
+/// away and compilation starts over.
+const CODE_SIZE: usize = 1 << 20;
+
+/// Longest block, in instructions.
+const MAX_BLOCK: usize = 64;
+
+/// Most versions of a block kept for one pc, for code that keeps patching
+/// itself. Past that the pc is interpreted.
+const MAX_VARIANTS: usize = 8;
+
+const EXIT_JUMP: u32 = 0;
+const EXIT_SLOW: u32 = 1;
+
+/// What compiled code sees. Offsets are baked into the generated code.
+#[repr(C)]
+struct State {
+    mem: *mut i64,
+    len: usize,
+    relative_offset: i64,
+    pc: usize,
+    /// `Jit::code`, as long as memory in bits.
+    code: *const u64,
+    /// `Jit::table`, as long as memory.
+    table: *const usize,
+}
+
+const STATE_PC: u8 = 24;
+const STATE_TABLE: u8 = 40;
+
+/// push rbx; push r12; push r13; push r14; push r15; mov rbx, rdi
+/// mov r12, [rbx]; mov r13, [rbx + 8]; mov r14, [rbx + 32]; mov r15, [rbx + 16]
+const PROLOGUE: [u8; 28] = [
+    0x53, 0x41, 0x54, 0x41, 0x55, 0x41, 0x56, 0x41, 0x57, 0x48, 0x89, 0xfb, 0x4c, 0x8b, 0x63, 0x00,
+    0x4c, 0x8b, 0x6b, 0x08, 0x4c, 0x8b, 0x73, 0x20, 0x4c, 0x8b, 0x7b, 0x10,
+];
+
+type Entry = unsafe extern "sysv64" fn(*mut State) -> u32;
+
+struct ExecBuffer {
+    ptr: *mut u8,
+    used: usize,
+}
+
+impl ExecBuffer {
+    fn new() -> Self {
+        let ptr = unsafe {
+            mmap(
+                ptr::null_mut(),
+                CODE_SIZE,
+                PROT_READ | PROT_WRITE | PROT_EXEC,
+                MAP_PRIVATE | MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        assert!(ptr as isize != -1, "mmap of JIT code buffer failed");
+        Self {
+            ptr: ptr as *mut u8,
+            used: 0,
+        }
+    }
+
+    fn push(&mut self, code: &[u8]) -> Option<Entry> {
+        if self.used + code.len() > CODE_SIZE {
+            return None;
+        }
+        unsafe {
+            let dst = self.ptr.add(self.used);
+            ptr::copy_nonoverlapping(code.as_ptr(), dst, code.len());
+            self.used += code.len();
+            Some(std::mem::transmute::<*mut u8, Entry>(dst))
+        }
+    }
+}
+
+impl Drop for ExecBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            munmap(self.ptr as *mut c_void, CODE_SIZE);
+        }
+    }
+}
+
+struct Block {
+    start: usize,
+    words: Vec<i64>,
+    /// The memory length the code's constant addresses rely on.
+    len: usize,
+    /// Immediate operands the code reads from memory instead, since the
+    /// program was seen storing to them. They may hold anything.
+    volatile: Vec<usize>,
+    /// `None` when the first instruction can't be compiled.
+    entry: Option<Entry>,
+}
+
+impl Block {
+    fn end(&self) -> usize {
+        self.start + self.words.len()
+    }
+
+    fn matches(&self, mem: &[i64]) -> bool {
+        let words = match mem.get(self.start..self.end()) {
+            Some(words) if mem.len() >= self.len => words,
+            _ => return false,
+        };
+        if self.volatile.is_empty() {
+            return words == &self.words[..];
+        }
+        (self.start..)
+            .zip(words.iter().zip(&self.words))
+            .all(|(addr, (word, compiled))| word == compiled || self.volatile.contains(&addr))
+    }
+}
+
+#[derive(Default)]
+struct Blocks {
+    buffer: Option<ExecBuffer>,
+    blocks: Vec<Block>,
+    by_pc: Vec<Vec<usize>>,
+    /// Bumped whenever the buffer is reused, leaving every entry point
+    /// handed out before dangling.
+    generation: u64,
+    /// The most words any block was compiled from.
+    longest: usize,
+    /// Words compiled code was seen storing to.
+    patched: HashSet<usize>,
+}
+
+impl Blocks {
+    fn clear(&mut self) {
+        self.blocks.clear();
+        self.by_pc.clear();
+        self.generation += 1;
+        if let Some(buffer) = &mut self.buffer {
+            buffer.used = 0;
+        }
+    }
+
+    /// Finds the block for `pc` that matches `mem`, compiling one if none
+    /// does, and returns its entry point, where it ends and its volatile
+    /// words.
+    fn entry(&mut self, mem: &[i64], pc: usize) -> Option<(Entry, usize, Vec<usize>)> {
+        let variants = self.by_pc.get(pc).map_or(&[][..], |v| &v[..]);
+        // The newest first, as it knows about the most patched words.
+        for &id in variants.iter().rev() {
+            let block = &self.blocks[id];
+            if block.matches(mem) {
+                return block
+                    .entry
+                    .map(|entry| (entry, block.end(), block.volatile.clone()));
+            }
+        }
+        if variants.len() >= MAX_VARIANTS || pc >= mem.len() {
+            return None;
+        }
+
+        let (words, len, volatile, code) = compile(mem, pc, &self.patched);
+        let end = pc + words.len();
+        self.longest = self.longest.max(words.len());
+        let buffer = self.buffer.get_or_insert_with(ExecBuffer::new);
+        let entry = match code {
+            Some(code) => match buffer.push(&code) {
+                Some(entry) => Some(entry),
+                None => {
+                    self.clear();
+                    self.buffer.as_mut()?.push(&code)
+                }
+            },
+            None => None,
+        };
+
+        if self.by_pc.len() <= pc {
+            self.by_pc.resize_with(pc + 1, Vec::new);
+        }
+        self.by_pc[pc].push(self.blocks.len());
+        self.blocks.push(Block {
+            start: pc,
+            words,
+            len,
+            volatile: volatile.clone(),
+            entry,
+        });
+        entry.map(|entry| (entry, end, volatile))
+    }
+}
+
+const RAX: u8 = 0;
+const RCX: u8 = 1;
+
+/// Condition codes for `jcc rel32`, the second opcode byte.
+const JO: u8 = 0x80;
+const JB: u8 = 0x82;
+const JAE: u8 = 0x83;
+const JE: u8 = 0x84;
+const JNE: u8 = 0x85;
+const JS: u8 = 0x88;
+
+/// How compiled code gets at a parameter.
+#[derive(Clone, Copy)]
+enum Operand {
+    Immediate(i64),
+    Position(i64),
+    Relative(i64),
+    /// A position parameter the program patches, so its address is read
+    /// from the word at runtime.
+    Indirect(usize),
+}
+
+impl Operand {
+    /// Compiles the parameter in `word`. Where the program patches the
+    /// word, the value is read from it when the block runs instead.
+    fn new(param: Param, word: usize, patched: &HashSet<usize>) -> Self {
+        let patched = patched.contains(&word);
+        match param.mode {
+            Mode::Immediate if patched => Operand::Position(word as i64),
+            Mode::Immediate => Operand::Immediate(param.value),
+            Mode::Position if patched => Operand::Indirect(word),
+            Mode::Position => Operand::Position(param.value),
+            Mode::Relative => Operand::Relative(param.value),
+        }
+    }
+}
+
+/// Emits the machine code for one block. Registers while it runs: `rbx`
+/// holds the `State`, `r12` the memory base, `r13` its length, `r14` the
+/// code bitmap and `r15` the relative base. Every block sets them up the
+/// same way, so one can jump into the next one's body.
+struct Asm {
+    code: Vec<u8>,
+    /// Branches to the slow-path stub for an instruction: where their
+    /// rel32 is, and the pc of the instruction.
+    slow: Vec<(usize, usize)>,
+    /// Jumps to the epilogue.
+    exits: Vec<usize>,
+    /// Memory length when the block was compiled. Memory only grows, so
+    /// constant addresses below it never need a bounds check.
+    len: usize,
+    /// One past the highest constant address used unchecked.
+    needs: usize,
+}
+
+impl Asm {
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.code.extend_from_slice(bytes);
+    }
+
+    fn imm32(&mut self, value: i32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn jcc_slow(&mut self, cc: u8, pc: usize) {
+        self.bytes(&[0x0f, cc]);
+        self.slow.push((self.code.len(), pc));
+        self.imm32(0);
+    }
+
+    fn jmp_slow(&mut self, pc: usize) {
+        self.bytes(&[0xe9]);
+        self.slow.push((self.code.len(), pc));
+        self.imm32(0);
+    }
+
+    fn jmp_exit(&mut self) {
+        self.bytes(&[0xe9]);
+        self.exits.push(self.code.len());
+        self.imm32(0);
+    }
+
+    /// Emits a short conditional jump to be patched later, returning where
+    /// its rel8 is.
+    fn jcc_short(&mut self, cc: u8) -> usize {
+        self.bytes(&[cc, 0]);
+        self.code.len() - 1
+    }
+
+    fn patch_short(&mut self, at: usize) {
+        self.code[at] = (self.code.len() - (at + 1)) as u8;
+    }
+
+    /// With `rax` loaded from the table, jumps into the body of the block
+    /// it holds, if any.
+    fn chain(&mut self) -> usize {
+        // test rax, rax; jz exit; jmp rax
+        self.bytes(&[0x48, 0x85, 0xc0]);
+        let exit = self.jcc_short(0x74);
+        self.bytes(&[0xff, 0xe0]);
+        exit
+    }
+
+    /// Continues at `pc`, in the block the table holds for it or else
+    /// back in the driver.
+    fn exit_to(&mut self, pc: usize, from: usize) {
+        let disp = pc.checked_mul(8).and_then(|disp| i32::try_from(disp).ok());
+        let (pc, disp) = match (i32::try_from(pc), disp) {
+            (Ok(pc), Some(disp)) => (pc, disp),
+            _ => return self.jmp_slow(from),
+        };
+        // cmp r13, pc; jbe exit; mov rax, [rbx + table]; mov rax, [rax + disp]
+        self.bytes(&[0x49, 0x81, 0xfd]);
+        self.imm32(pc);
+        let past = self.jcc_short(0x76);
+        self.bytes(&[0x48, 0x8b, 0x43, STATE_TABLE, 0x48, 0x8b, 0x80]);
+        self.imm32(disp);
+        let missing = self.chain();
+        self.patch_short(past);
+        self.patch_short(missing);
+        // mov qword [rbx + pc], imm32; mov eax, EXIT_JUMP
+        self.bytes(&[0x48, 0xc7, 0x43, STATE_PC]);
+        self.imm32(pc);
+        self.bytes(&[0xb8]);
+        self.imm32(EXIT_JUMP as i32);
+        self.jmp_exit();
+    }
+
+    /// Continues at the non-negative pc in `rcx`, like `exit_to`.
+    fn exit_to_rcx(&mut self) {
+        // cmp rcx, r13; jae exit; mov rax, [rbx + table]; mov rax, [rax + rcx * 8]
+        self.bytes(&[0x4c, 0x39, 0xe9]);
+        let past = self.jcc_short(0x73);
+        self.bytes(&[0x48, 0x8b, 0x43, STATE_TABLE, 0x48, 0x8b, 0x04, 0xc8]);
+        let missing = self.chain();
+        self.patch_short(past);
+        self.patch_short(missing);
+        // mov [rbx + pc], rcx; mov eax, EXIT_JUMP
+        self.bytes(&[0x48, 0x89, 0x4b, STATE_PC, 0xb8]);
+        self.imm32(EXIT_JUMP as i32);
+        self.jmp_exit();
+    }
+
+    /// Leaves `rdx` holding the address `rb + offset`, or takes the slow
+    /// path if it's outside memory.
+    fn relative_address(&mut self, offset: i64, pc: usize) -> bool {
+        let offset = match i32::try_from(offset) {
+            Ok(offset) => offset,
+            Err(_) => {
+                self.jmp_slow(pc);
+                return false;
+            }
+        };
+        // lea rdx, [r15 + offset]; cmp rdx, r13; jae slow
+        self.bytes(&[0x49, 0x8d, 0x97]);
+        self.imm32(offset);
+        self.bytes(&[0x4c, 0x39, 0xea]);
+        self.jcc_slow(JAE, pc);
+        true
+    }
+
+    /// Leaves `rdx` holding the address in `word`, like `relative_address`.
+    fn indirect_address(&mut self, word: usize, pc: usize) -> bool {
+        match self.constant_address(word as i64) {
+            Some(word) => {
+                // mov rdx, [r12 + word * 8]; cmp rdx, r13; jae slow
+                self.bytes(&[0x49, 0x8b, 0x94, 0x24]);
+                self.imm32(word as i32 * 8);
+                self.bytes(&[0x4c, 0x39, 0xea]);
+                self.jcc_slow(JAE, pc);
+                true
+            }
+            None => {
+                self.jmp_slow(pc);
+                false
+            }
+        }
+    }
+
+    /// Leaves `rdx` holding the address of a relative or indirect operand.
+    fn computed_address(&mut self, op: Operand, pc: usize) -> bool {
+        match op {
+            Operand::Relative(offset) => self.relative_address(offset, pc),
+            Operand::Indirect(word) => self.indirect_address(word, pc),
+            _ => unreachable!("address known at compile time"),
+        }
+    }
+
+    /// A constant address compiled code can use without a bounds check,
+    /// which its displacement in memory fits an `i32`.
+    fn constant_address(&mut self, addr: i64) -> Option<usize> {
+        let addr = usize::try_from(addr).ok().filter(|&a| a < self.len)?;
+        i32::try_from(addr * 8).ok()?;
+        self.needs = self.needs.max(addr + 1);
+        Some(addr)
+    }
+
+    fn load(&mut self, reg: u8, op: Operand, pc: usize) {
+        match op {
+            Operand::Immediate(value) => {
+                // mov reg, imm64
+                self.bytes(&[0x48, 0xb8 + reg]);
+                self.bytes(&value.to_le_bytes());
+            }
+            Operand::Position(addr) => match self.constant_address(addr) {
+                Some(addr) => {
+                    // mov reg, [r12 + addr * 8]
+                    self.bytes(&[0x49, 0x8b, 0x84 | reg << 3, 0x24]);
+                    self.imm32(addr as i32 * 8);
+                }
+                None => self.jmp_slow(pc),
+            },
+            Operand::Relative(_) | Operand::Indirect(_) => {
+                if self.computed_address(op, pc) {
+                    // mov reg, [r12 + rdx * 8]
+                    self.bytes(&[0x49, 0x8b, 0x04 | reg << 3, 0xd4]);
+                }
+            }
+        }
+    }
+
+    /// Stores `rax`. A store into compiled code, this block included, goes
+    /// through the slow path, so the driver can retire what it changes.
+    fn store(&mut self, op: Operand, pc: usize) {
+        match op {
+            Operand::Position(addr) => match self.constant_address(addr) {
+                Some(addr) => {
+                    // bt qword [r14 + addr / 64 * 8], addr % 64; jb slow
+                    self.bytes(&[0x49, 0x0f, 0xba, 0xa6]);
+                    self.imm32((addr / 64 * 8) as i32);
+                    self.bytes(&[(addr % 64) as u8]);
+                    self.jcc_slow(JB, pc);
+                    // mov [r12 + addr * 8], rax
+                    self.bytes(&[0x49, 0x89, 0x84, 0x24]);
+                    self.imm32(addr as i32 * 8);
+                }
+                None => self.jmp_slow(pc),
+            },
+            Operand::Relative(_) | Operand::Indirect(_) => {
+                if self.computed_address(op, pc) {
+                    // mov rcx, rdx; shr rcx, 6; mov rcx, [r14 + rcx * 8]
+                    // bt rcx, rdx; jb slow
+                    self.bytes(&[0x48, 0x89, 0xd1, 0x48, 0xc1, 0xe9, 0x06]);
+                    self.bytes(&[0x49, 0x8b, 0x0c, 0xce, 0x48, 0x0f, 0xa3, 0xd1]);
+                    self.jcc_slow(JB, pc);
+                    // mov [r12 + rdx * 8], rax
+                    self.bytes(&[0x49, 0x89, 0x04, 0xd4]);
+                }
+            }
+            Operand::Immediate(_) => self.jmp_slow(pc),
+        }
+    }
+
+    fn instruction(&mut self, instr: &Instruction, p: &[Operand]) {
+        let pc = instr.addr;
+        match instr.opcode {
+            Opcode::Add | Opcode::Mul | Opcode::Lt | Opcode::Eq => {
+                self.load(RAX, p[0], pc);
+                self.load(RCX, p[1], pc);
+                match instr.opcode {
+                    // add rax, rcx; jo slow
+                    Opcode::Add => {
+                        self.bytes(&[0x48, 0x01, 0xc8]);
+                        self.jcc_slow(JO, pc);
+                    }
+                    // imul rax, rcx; jo slow
+                    Opcode::Mul => {
+                        self.bytes(&[0x48, 0x0f, 0xaf, 0xc1]);
+                        self.jcc_slow(JO, pc);
+                    }
+                    // cmp rax, rcx; setl/sete al; movzx eax, al
+                    Opcode::Lt => {
+                        self.bytes(&[0x48, 0x39, 0xc8, 0x0f, 0x9c, 0xc0, 0x0f, 0xb6, 0xc0])
+                    }
+                    _ => self.bytes(&[0x48, 0x39, 0xc8, 0x0f, 0x94, 0xc0, 0x0f, 0xb6, 0xc0]),
+                }
+                self.store(p[2], pc);
+            }
+            Opcode::Arb => {
+                self.load(RAX, p[0], pc);
+                // mov rdx, r15; add rdx, rax; jo slow; mov r15, rdx
+                self.bytes(&[0x4c, 0x89, 0xfa, 0x48, 0x01, 0xc2]);
+                self.jcc_slow(JO, pc);
+                self.bytes(&[0x49, 0x89, 0xd7]);
+            }
+            Opcode::Jnz | Opcode::Jz => {
+                self.load(RAX, p[0], pc);
+                // test rax, rax; jcc not_taken
+                self.bytes(&[0x48, 0x85, 0xc0]);
+                let skip = if instr.opcode == Opcode::Jnz { JE } else { JNE };
+                self.bytes(&[0x0f, skip]);
+                let not_taken = self.code.len();
+                self.imm32(0);
+
+                match p[1] {
+                    Operand::Immediate(target) if target >= 0 => self.exit_to(target as usize, pc),
+                    Operand::Immediate(_) => self.jmp_slow(pc),
+                    _ => {
+                        self.load(RCX, p[1], pc);
+                        // test rcx, rcx; js slow
+                        self.bytes(&[0x48, 0x85, 0xc9]);
+                        self.jcc_slow(JS, pc);
+                        self.exit_to_rcx();
+                    }
+                }
+
+                self.patch(not_taken, self.code.len());
+                self.exit_to(pc + instr.size(), pc);
+            }
+            Opcode::In | Opcode::Out | Opcode::Hlt => unreachable!("not compiled"),
+        }
+    }
+
+    fn patch(&mut self, at: usize, target: usize) {
+        let rel = target as i64 - (at as i64 + 4);
+        self.code[at..at + 4].copy_from_slice(&(rel as i32).to_le_bytes());
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let epilogue = self.code.len();
+        // mov [rbx + 16], r15; pop r15; pop r14; pop r13; pop r12; pop rbx; ret
+        self.bytes(&[
+            0x4c, 0x89, 0x7b, 0x10, 0x41, 0x5f, 0x41, 0x5e, 0x41, 0x5d, 0x41, 0x5c, 0x5b, 0xc3,
+        ]);
+
+        let mut stubs: Vec<(usize, usize)> = Vec::new();
+        for (at, pc) in std::mem::take(&mut self.slow) {
+            let stub = match stubs.iter().find(|&&(p, _)| p == pc) {
+                Some(&(_, stub)) => stub,
+                None => {
+                    let stub = self.code.len();
+                    // mov qword [rbx + pc], imm32; mov eax, EXIT_SLOW; jmp epilogue
+                    self.bytes(&[0x48, 0xc7, 0x43, STATE_PC]);
+                    self.imm32(pc as i32);
+                    self.bytes(&[0xb8]);
+                    self.imm32(EXIT_SLOW as i32);
+                    self.bytes(&[0xe9]);
+                    let jmp = self.code.len();
+                    self.imm32(0);
+                    self.patch(jmp, epilogue);
+                    stubs.push((pc, stub));
+                    stub
+                }
+            };
+            self.patch(at, stub);
+        }
+        for at in std::mem::take(&mut self.exits) {
+            self.patch(at, epilogue);
+        }
+        self.code
+    }
+}
+
+type Compiled = (Vec<i64>, usize, Vec<usize>, Option<Vec<u8>>);
+
+/// Compiles the block starting at `pc`, reading immediates in `patched`
+/// from memory. Returns the words the code was compiled from, the memory
+/// length it needs, the immediates it reads and the code unless not even
+/// the first instruction can be compiled.
+fn compile(mem: &[i64], pc: usize, patched: &HashSet<usize>) -> Compiled {
+    let mut instructions = Vec::new();
+    let mut volatile = Vec::new();
+    let mut addr = pc;
+    while instructions.len() < MAX_BLOCK {
+        let instr = match Instruction::decode(mem, addr) {
+            Some(instr) if addr + instr.size() <= mem.len() && i32::try_from(addr).is_ok() => instr,
+            _ => break,
+        };
+        let opcode = instr.opcode;
+        if matches!(opcode, Opcode::In | Opcode::Out | Opcode::Hlt) {
+            break;
+        }
+        let words = addr + 1..;
+        let ops: Vec<_> = words
+            .clone()
+            .zip(&instr.params)
+            .map(|(word, &param)| Operand::new(param, word, patched))
+            .collect();
+        volatile.extend(
+            words
+                .zip(&instr.params)
+                .filter(|(word, param)| param.mode != Mode::Relative && patched.contains(word))
+                .map(|(word, _)| word),
+        );
+        addr += instr.size();
+        instructions.push((instr, ops));
+        if matches!(opcode, Opcode::Jnz | Opcode::Jz) {
+            break;
+        }
+    }
+
+    if instructions.is_empty() {
+        return (mem[pc..=pc].to_vec(), 0, Vec::new(), None);
+    }
+
+    let mut asm = Asm {
+        code: PROLOGUE.to_vec(),
+        slow: Vec::new(),
+        exits: Vec::new(),
+        len: mem.len(),
+        needs: 0,
+    };
+    for (instr, ops) in &instructions {
+        asm.instruction(instr, ops);
+    }
+    let (last, _) = instructions.last().unwrap();
+    if !matches!(last.opcode, Opcode::Jnz | Opcode::Jz) {
+        asm.exit_to(addr, last.addr);
+    }
+    let needs = asm.needs;
+    (mem[pc..addr].to_vec(), needs, volatile, Some(asm.finish()))
+}
+
+/// A machine that runs compiled blocks where it can and the interpreter
+/// elsewhere, with the same resumable API as `Cpu`.
+#[derive(Clone)]
+pub struct Jit {
+    cpu: Cpu,
+    blocks: Rc<RefCell<Blocks>>,
+    /// The generation of `blocks` the table was filled from.
+    generation: u64,
+    /// For each address, the body of the compiled block starting there
+    /// that was checked against this machine's memory, or 0.
+    table: Vec<usize>,
+    /// Where each block in `table` ends, or the next address where the
+    /// instruction is left to the interpreter. 0 where nothing was looked
+    /// up yet.
+    ends: Vec<usize>,
+    /// One bit for each word anything in `ends` covers, or once covered.
+    code: Vec<u64>,
+}
+
+impl Jit {
+    pub fn new(program: &[i64]) -> Self {
+        let mut cpu = Cpu::new(program);
+        // Compiled code writes memory behind the decode cache's back.
+        cpu.cache = DecodeCache::new(0);
+        let mut jit = Self {
+            cpu,
+            blocks: Rc::new(RefCell::new(Blocks::default())),
+            generation: 0,
+            table: Vec::new(),
+            ends: Vec::new(),
+            code: Vec::new(),
+        };
+        jit.pad();
+        jit.sync();
+
+        // Compile up front from every instruction the disassembler finds,
+        // so clones start out with the table filled in, treating the
+        // operands the program visibly patches as volatile from the start.
+        let disassembly = disasm::disassemble(program);
+        let instructions = &disassembly.instructions;
+        let operand = |addr: usize| {
+            let (&start, instr) = instructions.range(..addr).next_back()?;
+            let param = instr.params.get(addr - start - 1)?;
+            Some(param.mode != Mode::Relative)
+        };
+        let patched = instructions.values().filter_map(|instr| {
+            let param = instr.params[instr.opcode.write_param()?];
+            let addr = usize::try_from(param.value).ok()?;
+            (param.mode == Mode::Position && operand(addr)?).then_some(addr)
+        });
+        jit.blocks.borrow_mut().patched.extend(patched);
+        for &pc in instructions.keys() {
+            jit.lookup(pc);
+        }
+        jit
+    }
+
+    /// Grows memory to a power of two, so compiled code rarely finds an
+    /// address past its end. The zeros are as good as absent.
+    fn pad(&mut self) {
+        let limit = self.cpu.mem.limit();
+        let mem = self.cpu.mem.words_mut();
+        let padded = mem.len().next_power_of_two().min(limit).max(mem.len());
+        mem.resize(padded, 0);
+    }
+
+    /// The interpreter state compiled code runs on.
+    pub fn cpu(&self) -> &Cpu {
+        &self.cpu
+    }
+
+    pub fn set_overflow(&mut self, overflow: Overflow) {
+        self.cpu.set_overflow(overflow);
+    }
+
+    /// Forgets the table if the blocks in it were thrown away, and sizes
+    /// it and the bitmap to memory.
+    fn sync(&mut self) {
+        let generation = self.blocks.borrow().generation;
+        if generation != self.generation {
+            self.generation = generation;
+            self.table.iter_mut().for_each(|body| *body = 0);
+            self.ends.iter_mut().for_each(|end| *end = 0);
+            self.code.iter_mut().for_each(|bits| *bits = 0);
+        }
+        let len = self.cpu.mem.words_mut().len();
+        if self.table.len() != len {
+            self.table.resize(len, 0);
+            self.ends.resize(len, 0);
+            self.code.resize(len / 64 + 1, 0);
+        }
+    }
+
+    /// Looks up the block at `pc`, inside memory, for this machine's
+    /// memory and enters it in the table, returning its body or 0 if there
+    /// is none.
+    fn lookup(&mut self, pc: usize) -> usize {
+        let found = self.blocks.borrow_mut().entry(self.cpu.mem.words_mut(), pc);
+        // Compiling may have thrown away every block in the table.
+        self.sync();
+        let (body, end, volatile) = match found {
+            Some((entry, end, volatile)) => (entry as usize + PROLOGUE.len(), end, volatile),
+            None => (0, pc + 1, Vec::new()),
+        };
+        self.table[pc] = body;
+        self.ends[pc] = end;
+        for addr in (pc..end).filter(|addr| !volatile.contains(addr)) {
+            self.code[addr / 64] |= 1 << (addr % 64);
+        }
+        body
+    }
+
+    /// Where the instruction at pc stores to, if it does.
+    fn destination(&mut self) -> Option<usize> {
+        let mem = self.cpu.mem.words_mut();
+        let pc = self.cpu.pc;
+        let (code, modes) = decode(*mem.get(pc)?);
+        let param = Opcode::from_code(code)?.write_param()?;
+        let value = *mem.get(pc + 1 + param)?;
+        let addr = match modes[param] {
+            0 => value,
+            2 => self.cpu.relative_offset.checked_add(value)?,
+            _ => return None,
+        };
+        usize::try_from(addr).ok()
+    }
+
+    /// Takes every block covering `addr` out of the table after a store
+    /// there.
+    fn retire(&mut self, addr: usize) {
+        if self
+            .code
+            .get(addr / 64)
+            .is_none_or(|bits| bits & 1 << (addr % 64) == 0)
+        {
+            return;
+        }
+        let mut blocks = self.blocks.borrow_mut();
+        for pc in addr.saturating_sub(blocks.longest)..=addr {
+            if self.ends[pc] > addr {
+                self.table[pc] = 0;
+                self.ends[pc] = 0;
+            }
+        }
+        // Nothing in the table covers `addr` now. Blocks compiled from here
+        // on read it from memory if it's an immediate, so it can go
+        // unchecked. The other retired blocks' bits stay set, which only
+        // costs later stores the slow path.
+        self.code[addr / 64] &= !(1 << (addr % 64));
+        blocks.patched.insert(addr);
+    }
+}
+
+impl Machine for Jit {
+    fn push_input(&mut self, value: i64) {
+        self.cpu.push_input(value);
+    }
+
+    fn run(&mut self) -> Result<Status, IntcodeError> {
+        loop {
+            self.sync();
+            let pc = self.cpu.pc;
+            let body = match self.table.get(pc) {
+                Some(0) if self.ends[pc] == 0 => self.lookup(pc),
+                Some(&body) => body,
+                None => 0,
+            };
+            if body != 0 {
+                let mem = self.cpu.mem.words_mut();
+                let mut state = State {
+                    mem: mem.as_mut_ptr(),
+                    len: mem.len(),
+                    relative_offset: self.cpu.relative_offset,
+                    pc,
+                    code: self.code.as_ptr(),
+                    table: self.table.as_ptr(),
+                };
+                // Safety: every block in the table was compiled from the
+                // words memory holds, and memory is long enough for its
+                // constant addresses. Blocks only touch `state`, the table,
+                // the bitmap and memory inside `len`, and leave before
+                // storing into any of their own words.
+                let exit = unsafe {
+                    let entry = std::mem::transmute::<usize, Entry>(body - PROLOGUE.len());
+                    entry(&mut state)
+                };
+                self.cpu.relative_offset = state.relative_offset;
+                self.cpu.pc = state.pc;
+                if exit == EXIT_JUMP {
+                    continue;
+                }
+            }
+
+            let len = self.cpu.mem.words_mut().len();
+            let stored = self.destination();
+            let status = self.cpu.step();
+            if let Some(addr) = stored {
+                self.retire(addr);
+            }
+            if self.cpu.mem.words_mut().len() > len {
+                self.pad();
+            }
+            if let Some(status) = status? {
+                return Ok(status);
+            }
+        }
+    }
+}
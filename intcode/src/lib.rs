@@ -0,0 +1,50 @@
+pub mod aot;
+pub mod ascii;
+pub mod asm;
+mod cache;
+mod cell;
+pub mod cfg;
+mod cpu;
+pub mod debugger;
+pub mod decompile;
+pub mod disasm;
+mod error;
+pub mod fuzz;
+pub mod io;
+#[cfg(all(feature = "jit", target_arch = "x86_64", target_os = "linux"))]
+pub mod jit;
+mod memory;
+pub mod network;
+mod observer;
+mod opcode;
+mod overflow;
+pub mod profile;
+pub mod promote;
+pub mod record;
+mod selfmod;
+pub mod snapshot;
+pub mod symbolic;
+pub mod trace;
+
+pub use cell::Cell;
+pub use cpu::{Cpu, Machine, Status};
+pub use error::{ErrorKind, IntcodeError};
+pub use io::{InputSource, OutputSink};
+pub use memory::{Dense, Hybrid, Memory, Sparse};
+pub use observer::Observer;
+pub use opcode::{decode, Mode, Opcode, OPCODES};
+pub use overflow::Overflow;
+pub use selfmod::CodeWrite;
+
+pub use num_bigint::BigInt;
+
+use std::str::FromStr;
+
+/// Parses a program in the comma-separated format of the puzzle inputs,
+/// into cells of any type.
+pub fn parse_program<C: FromStr>(s: &str) -> Result<Vec<C>, C::Err> {
+    s.trim()
+        .split(',')
+        .map(|value| value.trim().parse())
+        .collect()
+}
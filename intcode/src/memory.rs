@@ -0,0 +1,238 @@
+use crate::cell::Cell;
+use std::collections::HashMap;
+use std::ops::Index;
+use std::ops::IndexMut;
+
+/// Storage for a CPU's address space. Every cell starts out as zero;
+/// reading through `Index` never allocates, writing through `IndexMut`
+/// makes room for the cell as long as `fits` allows it.
+pub trait Memory:
+    Clone + Default + Index<usize, Output = <Self as Memory>::Cell> + IndexMut<usize>
+{
+    type Cell: Cell;
+
+    /// Whether a cell can be written at `addr`. The CPU faults on stores
+    /// anywhere else instead of letting the backend run out of memory.
+    fn fits(&self, _addr: usize) -> bool {
+        true
+    }
+
+    /// Returns every non-zero cell, in address order.
+    fn cells(&self) -> Vec<(usize, Self::Cell)>;
+
+    /// Copies `program` to the start of memory.
+    fn load(&mut self, program: &[Self::Cell]) {
+        for (addr, value) in program.iter().enumerate() {
+            self[addr] = value.clone();
+        }
+    }
+}
+
+/// A flat vector grown to the highest address written. The fastest
+/// backend, but a single write to a huge address allocates everything
+/// below it, so addresses from `limit` on don't fit.
+#[derive(Clone)]
+pub struct Dense<C = i64> {
+    mem: Vec<C>,
+    zero: C,
+    limit: usize,
+}
+
+impl<C: Cell> Dense<C> {
+    /// 16M cells, far more than any puzzle uses.
+    pub const DEFAULT_LIMIT: usize = 1 << 24;
+
+    pub fn new() -> Self {
+        Self::with_limit(Self::DEFAULT_LIMIT)
+    }
+
+    pub fn with_limit(limit: usize) -> Self {
+        Self {
+            mem: Vec::new(),
+            zero: C::default(),
+            limit,
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+}
+
+impl<C: Cell> Default for Dense<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(feature = "jit", target_arch = "x86_64", target_os = "linux"))]
+impl Dense {
+    /// The cells up to the highest one written; everything past it is zero.
+    pub(crate) fn words_mut(&mut self) -> &mut Vec<i64> {
+        &mut self.mem
+    }
+}
+
+impl<C: Cell> Memory for Dense<C> {
+    type Cell = C;
+
+    fn fits(&self, addr: usize) -> bool {
+        addr < self.limit
+    }
+
+    fn cells(&self) -> Vec<(usize, C)> {
+        self.mem
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| !value.is_zero())
+            .map(|(addr, value)| (addr, value.clone()))
+            .collect()
+    }
+
+    fn load(&mut self, program: &[C]) {
+        if self.mem.len() < program.len() {
+            self.mem.resize(program.len(), C::default());
+        }
+        self.mem[..program.len()].clone_from_slice(program);
+    }
+}
+
+impl<C> Index<usize> for Dense<C> {
+    type Output = C;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        self.mem.get(index).unwrap_or(&self.zero)
+    }
+}
+
+impl<C: Cell> IndexMut<usize> for Dense<C> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        if index >= self.mem.len() {
+            assert!(
+                index < self.limit,
+                "address {} is past the memory limit",
+                index
+            );
+            self.mem.resize(index + 1, C::default());
+        }
+        &mut self.mem[index]
+    }
+}
+
+/// A hash map holding only the cells that were written, for programs
+/// that scatter writes across huge addresses.
+#[derive(Clone, Default)]
+pub struct Sparse<C = i64> {
+    mem: HashMap<usize, C>,
+    zero: C,
+}
+
+impl<C: Cell> Sparse<C> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<C: Cell> Memory for Sparse<C> {
+    type Cell = C;
+
+    fn cells(&self) -> Vec<(usize, C)> {
+        let mut cells: Vec<_> = self
+            .mem
+            .iter()
+            .filter(|(_, value)| !value.is_zero())
+            .map(|(&addr, value)| (addr, value.clone()))
+            .collect();
+        cells.sort_unstable();
+        cells
+    }
+}
+
+impl<C> Index<usize> for Sparse<C> {
+    type Output = C;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        self.mem.get(&index).unwrap_or(&self.zero)
+    }
+}
+
+impl<C: Cell> IndexMut<usize> for Sparse<C> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        self.mem.entry(index).or_default()
+    }
+}
+
+/// Dense below `limit` and sparse from there on, so the program image and
+/// its stack stay fast while stray high addresses cost only what they use.
+#[derive(Clone)]
+pub struct Hybrid<C = i64> {
+    dense: Dense<C>,
+    sparse: Sparse<C>,
+    limit: usize,
+}
+
+impl<C: Cell> Hybrid<C> {
+    pub const DEFAULT_LIMIT: usize = 1 << 20;
+
+    pub fn new() -> Self {
+        Self::with_limit(Self::DEFAULT_LIMIT)
+    }
+
+    pub fn with_limit(limit: usize) -> Self {
+        Self {
+            dense: Dense::with_limit(limit),
+            sparse: Sparse::new(),
+            limit,
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+}
+
+impl<C: Cell> Default for Hybrid<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Cell> Memory for Hybrid<C> {
+    type Cell = C;
+
+    fn cells(&self) -> Vec<(usize, C)> {
+        let mut cells = self.dense.cells();
+        cells.extend(self.sparse.cells());
+        cells
+    }
+
+    fn load(&mut self, program: &[C]) {
+        let split = program.len().min(self.limit);
+        self.dense.load(&program[..split]);
+        for (addr, value) in program.iter().enumerate().skip(split) {
+            self.sparse[addr] = value.clone();
+        }
+    }
+}
+
+impl<C: Cell> Index<usize> for Hybrid<C> {
+    type Output = C;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        if index < self.limit {
+            &self.dense[index]
+        } else {
+            &self.sparse[index]
+        }
+    }
+}
+
+impl<C: Cell> IndexMut<usize> for Hybrid<C> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        if index < self.limit {
+            &mut self.dense[index]
+        } else {
+            &mut self.sparse[index]
+        }
+    }
+}
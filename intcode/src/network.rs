@@ -0,0 +1,648 @@
+//! Running several machines wired together.
+//!
+//! A `Topology` says, as plain data, what each machine starts with, where
+//! its outputs go and what it reads when its queue is empty. A `Network`
+//! then runs the machines round-robin and reports whatever leaves the
+//! network, or the network going idle, to the caller as an `Event`:
+//!
+//! ```text
+//! let topology = Topology::ring(5).input(0, &[0]).route(4, Route::Send(vec![Target::Node(0), Target::External]));
+//! let mut network = Network::new(topology, &cpu)?;
+//! while let Event::Output { value, .. } = network.run()? { ... }
+//! ```
+//!
+//! `Threaded` runs the same topologies with a thread per machine, for when
+//! the order nodes run in doesn't matter.
+
+use crate::cpu::{Machine, Status};
+use crate::error::IntcodeError;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::Ordering::SeqCst;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    Node(usize),
+    /// Out of the network, as an `Event::Output`.
+    External,
+}
+
+/// Where a node's outputs go.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Route {
+    /// Every output goes to each target: one target for a pipe, more to
+    /// broadcast.
+    Send(Vec<Target>),
+    /// Outputs form packets of `len` words, the first of which is an
+    /// address. The rest of the packet goes to the node with that index,
+    /// or out of the network as an `Event::Packet` if there is none.
+    Bus { len: usize },
+}
+
+impl Default for Route {
+    fn default() -> Self {
+        Route::Send(vec![Target::External])
+    }
+}
+
+/// What a node gets when it wants input and its queue is empty.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OnEmpty {
+    /// Nothing; the node waits until something is sent to it.
+    #[default]
+    Block,
+    /// This value, once per turn, for programs that poll.
+    Value(i64),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NodeSpec {
+    /// Queued before the node first runs.
+    pub input: Vec<i64>,
+    pub route: Route,
+    pub on_empty: OnEmpty,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Topology {
+    pub nodes: Vec<NodeSpec>,
+}
+
+impl Topology {
+    /// `n` unconnected nodes, all sending their output out of the network.
+    pub fn new(n: usize) -> Self {
+        Self {
+            nodes: vec![NodeSpec::default(); n],
+        }
+    }
+
+    /// Node `i` feeds node `i + 1`; the last one's output leaves the
+    /// network.
+    pub fn pipeline(n: usize) -> Self {
+        let mut topology = Self::new(n);
+        for i in 1..n {
+            topology.nodes[i - 1].route = Route::Send(vec![Target::Node(i)]);
+        }
+        topology
+    }
+
+    /// A pipeline whose last node feeds the first.
+    pub fn ring(n: usize) -> Self {
+        let mut topology = Self::pipeline(n);
+        if n > 0 {
+            topology.nodes[n - 1].route = Route::Send(vec![Target::Node(0)]);
+        }
+        topology
+    }
+
+    /// Nodes that all send packets of `len` words over a shared bus,
+    /// addressed by node index.
+    pub fn bus(n: usize, len: usize) -> Self {
+        let mut topology = Self::new(n);
+        for node in &mut topology.nodes {
+            node.route = Route::Bus { len };
+        }
+        topology
+    }
+
+    /// Queues `values` for `node` before it first runs.
+    pub fn input(mut self, node: usize, values: &[i64]) -> Self {
+        self.nodes[node].input.extend_from_slice(values);
+        self
+    }
+
+    pub fn route(mut self, node: usize, route: Route) -> Self {
+        self.nodes[node].route = route;
+        self
+    }
+
+    /// Sets what every node reads from an empty queue.
+    pub fn on_empty(mut self, on_empty: OnEmpty) -> Self {
+        for node in &mut self.nodes {
+            node.on_empty = on_empty;
+        }
+        self
+    }
+
+    /// Checks that every route targets a node the topology has.
+    pub fn validate(&self) -> Result<(), TopologyError> {
+        for (node, spec) in self.nodes.iter().enumerate() {
+            if let Route::Send(targets) = &spec.route {
+                for &target in targets {
+                    match target {
+                        Target::Node(to) if to >= self.nodes.len() => {
+                            return Err(TopologyError { node, to })
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A route to a node the topology doesn't have.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TopologyError {
+    pub node: usize,
+    pub to: usize,
+}
+
+impl fmt::Display for TopologyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "node {} sends to missing node {}", self.node, self.to)
+    }
+}
+
+impl Error for TopologyError {}
+
+/// Something the network needs the caller for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    Output {
+        from: usize,
+        value: i64,
+    },
+    /// A bus packet for an address no node has.
+    Packet {
+        from: usize,
+        addr: i64,
+        words: Vec<i64>,
+    },
+    /// A whole round went by with every queue empty and no node taking
+    /// input or producing output. Sending something to a node gets the
+    /// network going again.
+    Idle,
+    /// Every node has halted.
+    Halted,
+}
+
+/// Runs a network until it needs the caller. `Network` is one
+/// deterministic thread; `Threaded` runs every node on its own.
+pub trait Scheduler {
+    /// Queues `values` for `node`.
+    fn send(&mut self, node: usize, values: &[i64]);
+    /// Runs the network until the next event.
+    fn run(&mut self) -> Result<Event, IntcodeError>;
+}
+
+/// A node's route, and the packet it is partway through sending.
+struct Outbox {
+    route: Route,
+    packet: Vec<i64>,
+}
+
+enum Delivery<'a> {
+    Targets(&'a [Target]),
+    /// An address and the rest of the packet.
+    Packet(i64, &'a [i64]),
+}
+
+impl Outbox {
+    fn new(route: Route) -> Self {
+        Self {
+            route,
+            packet: Vec::new(),
+        }
+    }
+
+    /// Where `value` goes, once it completes whatever it is part of.
+    fn push(&mut self, value: i64) -> Option<Delivery<'_>> {
+        match &self.route {
+            Route::Send(targets) => Some(Delivery::Targets(targets)),
+            &Route::Bus { len } => {
+                if self.packet.len() == len {
+                    self.packet.clear();
+                }
+                self.packet.push(value);
+                if self.packet.len() < len {
+                    return None;
+                }
+                Some(Delivery::Packet(self.packet[0], &self.packet[1..]))
+            }
+        }
+    }
+}
+
+/// The node a bus address belongs to.
+fn node_at(addr: i64, nodes: usize) -> Option<usize> {
+    usize::try_from(addr).ok().filter(|&node| node < nodes)
+}
+
+struct Node<T> {
+    machine: T,
+    queue: VecDeque<i64>,
+    on_empty: OnEmpty,
+    halted: bool,
+}
+
+/// The machines of a `Topology`, scheduled one turn at a time. A turn runs
+/// a node until it halts or wants input its queue doesn't have.
+pub struct Network<T> {
+    nodes: Vec<Node<T>>,
+    outboxes: Vec<Outbox>,
+    events: VecDeque<Event>,
+    next: usize,
+    active: bool,
+}
+
+impl<T: Machine + Clone> Network<T> {
+    /// Runs a copy of `machine` on every node of `topology`.
+    pub fn new(topology: Topology, machine: &T) -> Result<Self, TopologyError> {
+        Self::with_machines(topology, |_| machine.clone())
+    }
+}
+
+impl<T: Machine> Network<T> {
+    /// Runs `machine(i)` on node `i` of `topology`.
+    pub fn with_machines(
+        topology: Topology,
+        mut machine: impl FnMut(usize) -> T,
+    ) -> Result<Self, TopologyError> {
+        topology.validate()?;
+        let mut nodes = Vec::new();
+        let mut outboxes = Vec::new();
+        for (i, spec) in topology.nodes.into_iter().enumerate() {
+            nodes.push(Node {
+                machine: machine(i),
+                queue: spec.input.into(),
+                on_empty: spec.on_empty,
+                halted: false,
+            });
+            outboxes.push(Outbox::new(spec.route));
+        }
+        Ok(Self {
+            nodes,
+            outboxes,
+            events: VecDeque::new(),
+            next: 0,
+            active: false,
+        })
+    }
+
+    /// Queues `values` for `node`.
+    pub fn send(&mut self, node: usize, values: &[i64]) {
+        self.nodes[node].queue.extend(values);
+    }
+
+    /// Runs the network until the next event.
+    pub fn run(&mut self) -> Result<Event, IntcodeError> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(event);
+            }
+            if self.nodes.iter().all(|node| node.halted) {
+                return Ok(Event::Halted);
+            }
+
+            let i = self.next;
+            if !self.nodes[i].halted {
+                self.turn(i)?;
+            }
+
+            self.next = (i + 1) % self.nodes.len();
+            if self.next == 0 {
+                let idle = !self.active && self.nodes.iter().all(|node| node.queue.is_empty());
+                self.active = false;
+                if idle && self.events.is_empty() {
+                    return Ok(Event::Idle);
+                }
+            }
+        }
+    }
+
+    fn turn(&mut self, i: usize) -> Result<(), IntcodeError> {
+        loop {
+            let node = &mut self.nodes[i];
+            match node.machine.run()? {
+                Status::Output(value) => {
+                    self.active = true;
+                    self.deliver(i, value);
+                }
+                Status::NeedsInput => match node.queue.pop_front() {
+                    Some(value) => {
+                        self.active = true;
+                        node.machine.push_input(value);
+                    }
+                    None => {
+                        if let OnEmpty::Value(value) = node.on_empty {
+                            node.machine.push_input(value);
+                        }
+                        return Ok(());
+                    }
+                },
+                Status::Halted => {
+                    node.halted = true;
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    fn deliver(&mut self, from: usize, value: i64) {
+        match self.outboxes[from].push(value) {
+            None => {}
+            Some(Delivery::Targets(targets)) => {
+                for &target in targets {
+                    match target {
+                        Target::Node(to) => self.nodes[to].queue.push_back(value),
+                        Target::External => self.events.push_back(Event::Output { from, value }),
+                    }
+                }
+            }
+            Some(Delivery::Packet(addr, words)) => match node_at(addr, self.nodes.len()) {
+                Some(to) => self.nodes[to].queue.extend(words),
+                None => self.events.push_back(Event::Packet {
+                    from,
+                    addr,
+                    words: words.to_vec(),
+                }),
+            },
+        }
+    }
+}
+
+impl<T: Machine> Scheduler for Network<T> {
+    fn send(&mut self, node: usize, values: &[i64]) {
+        Network::send(self, node, values)
+    }
+
+    fn run(&mut self) -> Result<Event, IntcodeError> {
+        Network::run(self)
+    }
+}
+
+/// How many instructions a `Threaded` node runs between checks for the
+/// network stopping.
+const SLICE: u64 = 10_000;
+
+/// How long `Threaded::run` waits for an event before checking whether the
+/// network has gone quiet.
+const TICK: Duration = Duration::from_millis(1);
+
+/// What the threads of a `Threaded` network share.
+struct Shared {
+    /// Bumped whenever a node takes input or produces output.
+    epoch: AtomicU64,
+    /// Values sent to each node and not yet taken.
+    pending: Vec<AtomicUsize>,
+    /// How many times in a row each node found its queue empty;
+    /// `usize::MAX` while it blocks.
+    polls: Vec<AtomicUsize>,
+    halted: Vec<AtomicBool>,
+    stop: AtomicBool,
+}
+
+impl Shared {
+    fn sent(&self, to: usize, count: usize) {
+        self.pending[to].fetch_add(count, SeqCst);
+        self.epoch.fetch_add(1, SeqCst);
+    }
+
+    fn took(&self, node: usize) {
+        self.pending[node].fetch_sub(1, SeqCst);
+        self.polls[node].store(0, SeqCst);
+        self.epoch.fetch_add(1, SeqCst);
+    }
+
+    /// Every node has halted, or has an empty queue and has blocked or
+    /// polled `polls` times in a row, with nothing happening while we
+    /// looked.
+    fn is_quiet(&self, polls: usize) -> bool {
+        let epoch = self.epoch.load(SeqCst);
+        let quiet = (0..self.pending.len()).all(|i| {
+            self.halted[i].load(SeqCst)
+                || (self.pending[i].load(SeqCst) == 0 && self.polls[i].load(SeqCst) >= polls)
+        });
+        quiet && self.epoch.load(SeqCst) == epoch
+    }
+}
+
+/// The sending side of every node's input. A packet goes as one message,
+/// so packets from different senders can't interleave.
+#[derive(Clone)]
+struct Links {
+    inputs: Vec<Sender<Vec<i64>>>,
+    shared: Arc<Shared>,
+}
+
+impl Links {
+    fn send(&self, to: usize, values: &[i64]) {
+        if values.is_empty() {
+            return;
+        }
+        self.shared.sent(to, values.len());
+        if self.inputs[to].send(values.to_vec()).is_err() {
+            // The node has halted and will never take them.
+            self.shared.pending[to].fetch_sub(values.len(), SeqCst);
+        }
+    }
+}
+
+/// The machines of a `Topology`, each running on a thread of its own and
+/// talking over channels. Events come in the order the threads produce
+/// them, so unlike `Network` the results can vary from run to run.
+///
+/// The network is idle once every queue is empty and every node is
+/// blocked on input, or has found its queue empty for `polls` polls in a
+/// row if it reads `OnEmpty::Value`. Polls are only a guess at what a node
+/// is doing: one that computes for a while after its last empty poll, and
+/// only then sends something, can be taken for idle in the meantime. Pick
+/// `polls` high enough for the program's quiet stretches.
+pub struct Threaded {
+    links: Links,
+    events: Receiver<Result<Event, IntcodeError>>,
+    threads: Vec<JoinHandle<()>>,
+    polls: usize,
+}
+
+impl Threaded {
+    /// Runs a copy of `machine` on every node of `topology`.
+    pub fn new<T>(topology: Topology, machine: &T, polls: usize) -> Result<Self, TopologyError>
+    where
+        T: Machine + Clone + Send + 'static,
+    {
+        topology.validate()?;
+        let n = topology.nodes.len();
+        let (events_tx, events) = mpsc::channel();
+        let (inputs, receivers): (Vec<_>, Vec<_>) = (0..n).map(|_| mpsc::channel()).unzip();
+        let links = Links {
+            inputs,
+            shared: Arc::new(Shared {
+                epoch: AtomicU64::new(0),
+                pending: (0..n).map(|_| AtomicUsize::new(0)).collect(),
+                polls: (0..n).map(|_| AtomicUsize::new(0)).collect(),
+                halted: (0..n).map(|_| AtomicBool::new(false)).collect(),
+                stop: AtomicBool::new(false),
+            }),
+        };
+
+        // Queue every node's input before any node can send it something.
+        for (i, spec) in topology.nodes.iter().enumerate() {
+            links.send(i, &spec.input);
+        }
+        let mut threads = Vec::new();
+        for (i, (spec, input)) in topology.nodes.into_iter().zip(receivers).enumerate() {
+            let machine = machine.clone();
+            let links = links.clone();
+            let events = events_tx.clone();
+            threads.push(thread::spawn(move || {
+                let result = serve(i, machine, spec, &input, &links, &events);
+                links.shared.halted[i].store(true, SeqCst);
+                if let Err(err) = result {
+                    let _ = events.send(Err(err));
+                }
+            }));
+        }
+
+        Ok(Self {
+            links,
+            events,
+            threads,
+            polls,
+        })
+    }
+
+    /// Queues `values` for `node`.
+    pub fn send(&mut self, node: usize, values: &[i64]) {
+        self.links.send(node, values);
+    }
+
+    /// Waits for the next event.
+    pub fn run(&mut self) -> Result<Event, IntcodeError> {
+        loop {
+            match self.events.recv_timeout(TICK) {
+                Ok(event) => return event,
+                Err(RecvTimeoutError::Disconnected) => return Ok(Event::Halted),
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+            if self.links.shared.is_quiet(self.polls) {
+                return Ok(Event::Idle);
+            }
+        }
+    }
+}
+
+/// Stops every node within `SLICE` instructions, or at its next input or
+/// output if its machine can't stop partway, and waits for it.
+impl Drop for Threaded {
+    fn drop(&mut self) {
+        self.links.shared.stop.store(true, SeqCst);
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Scheduler for Threaded {
+    fn send(&mut self, node: usize, values: &[i64]) {
+        Threaded::send(self, node, values)
+    }
+
+    fn run(&mut self) -> Result<Event, IntcodeError> {
+        Threaded::run(self)
+    }
+}
+
+/// Runs node `i` of a `Threaded` network until it halts or the network
+/// stops.
+fn serve<T: Machine>(
+    i: usize,
+    mut machine: T,
+    spec: NodeSpec,
+    input: &Receiver<Vec<i64>>,
+    links: &Links,
+    events: &Sender<Result<Event, IntcodeError>>,
+) -> Result<(), IntcodeError> {
+    let shared = &links.shared;
+    let nodes = links.inputs.len();
+    let event = |event| {
+        shared.epoch.fetch_add(1, SeqCst);
+        let _ = events.send(Ok(event));
+    };
+    let mut outbox = Outbox::new(spec.route);
+    let mut queue = VecDeque::new();
+    while !shared.stop.load(SeqCst) {
+        let status = match machine.run_for(SLICE)? {
+            Some(status) => status,
+            None => continue,
+        };
+        match status {
+            Status::Output(value) => {
+                shared.polls[i].store(0, SeqCst);
+                match outbox.push(value) {
+                    None => {}
+                    Some(Delivery::Targets(targets)) => {
+                        for &target in targets {
+                            match target {
+                                Target::Node(to) => links.send(to, &[value]),
+                                Target::External => event(Event::Output { from: i, value }),
+                            }
+                        }
+                    }
+                    Some(Delivery::Packet(addr, words)) => match node_at(addr, nodes) {
+                        Some(to) => links.send(to, words),
+                        None => event(Event::Packet {
+                            from: i,
+                            addr,
+                            words: words.to_vec(),
+                        }),
+                    },
+                }
+            }
+            Status::NeedsInput => {
+                if queue.is_empty() {
+                    if let Ok(values) = input.try_recv() {
+                        queue.extend(values);
+                    }
+                }
+                let value = match (queue.pop_front(), spec.on_empty) {
+                    (Some(value), _) => Some(value),
+                    (None, OnEmpty::Value(value)) => {
+                        let polls = shared.polls[i].load(SeqCst);
+                        shared.polls[i].store(polls.saturating_add(1), SeqCst);
+                        machine.push_input(value);
+                        thread::yield_now();
+                        continue;
+                    }
+                    (None, OnEmpty::Block) => {
+                        shared.polls[i].store(usize::MAX, SeqCst);
+                        wait(input, shared).and_then(|values| {
+                            queue.extend(values);
+                            queue.pop_front()
+                        })
+                    }
+                };
+                match value {
+                    Some(value) => {
+                        shared.took(i);
+                        machine.push_input(value);
+                    }
+                    None => break,
+                }
+            }
+            Status::Halted => break,
+        }
+    }
+    Ok(())
+}
+
+/// Blocks until values arrive or the network stops.
+fn wait(input: &Receiver<Vec<i64>>, shared: &Shared) -> Option<Vec<i64>> {
+    while !shared.stop.load(SeqCst) {
+        match input.recv_timeout(TICK) {
+            Ok(values) => return Some(values),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    None
+}
@@ -0,0 +1,30 @@
+/// Hooks into instruction execution. Every method has an empty default, so
+/// an observer only implements the events it cares about. `C` is the
+/// CPU's cell type.
+pub trait Observer<C = i64> {
+    /// Lets the CPU skip the bookkeeping for observers that ignore every
+    /// event, such as `()`.
+    const ENABLED: bool = true;
+
+    /// Called before the instruction at `pc` executes, with the instruction
+    /// word followed by its raw parameters.
+    fn instruction(&mut self, _pc: usize, _words: &[C]) {}
+
+    /// Called when parameter `index` is read, with its value after mode
+    /// handling.
+    fn param(&mut self, _index: usize, _value: C) {}
+
+    /// Called when a position or relative parameter reads memory.
+    fn read(&mut self, _addr: usize, _value: C) {}
+
+    /// Called before an instruction writes `new` over `old` at `addr`.
+    fn write(&mut self, _addr: usize, _old: C, _new: C) {}
+
+    /// Called once the instruction at `pc` has executed. An input
+    /// instruction that stops with `NeedsInput` has not executed.
+    fn executed(&mut self, _pc: usize) {}
+}
+
+impl<C> Observer<C> for () {
+    const ENABLED: bool = false;
+}
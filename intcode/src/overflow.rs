@@ -0,0 +1,69 @@
+use crate::cell::Cell;
+use crate::error::ErrorKind;
+use std::fmt;
+use std::str::FromStr;
+
+/// What `add` and `mul` do when the result doesn't fit in a cell.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// Stop with `ErrorKind::Overflow`, leaving pc on the instruction.
+    #[default]
+    Trap,
+    /// Wrap around, two's complement.
+    Wrap,
+    /// Clamp to the smallest or largest cell value.
+    Saturate,
+    /// Carry on with arbitrary-precision cells. A `Cpu` can't change its
+    /// cell type, so on its own it traps; `Promoting` catches the trap and
+    /// moves the program onto big integers.
+    Promote,
+}
+
+impl Overflow {
+    #[inline]
+    pub(crate) fn add<C: Cell>(self, a: &C, b: &C) -> Result<C, ErrorKind> {
+        match self {
+            Overflow::Trap | Overflow::Promote => a
+                .checked_add(b)
+                .ok_or_else(|| ErrorKind::Overflow(a.clamped(), b.clamped())),
+            Overflow::Wrap => Ok(a.wrapping_add(b)),
+            Overflow::Saturate => Ok(a.saturating_add(b)),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn mul<C: Cell>(self, a: &C, b: &C) -> Result<C, ErrorKind> {
+        match self {
+            Overflow::Trap | Overflow::Promote => a
+                .checked_mul(b)
+                .ok_or_else(|| ErrorKind::Overflow(a.clamped(), b.clamped())),
+            Overflow::Wrap => Ok(a.wrapping_mul(b)),
+            Overflow::Saturate => Ok(a.saturating_mul(b)),
+        }
+    }
+}
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Overflow::Trap => "trap",
+            Overflow::Wrap => "wrap",
+            Overflow::Saturate => "saturate",
+            Overflow::Promote => "promote",
+        })
+    }
+}
+
+impl FromStr for Overflow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "trap" => Ok(Overflow::Trap),
+            "wrap" => Ok(Overflow::Wrap),
+            "saturate" => Ok(Overflow::Saturate),
+            "promote" => Ok(Overflow::Promote),
+            _ => Err(format!("unknown overflow policy: {}", s)),
+        }
+    }
+}
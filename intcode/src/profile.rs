@@ -0,0 +1,337 @@
+use crate::cell::Cell;
+use crate::disasm::Instruction;
+use crate::observer::Observer;
+use crate::opcode::{decode, Mode, Opcode};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::io::{self, Write};
+
+/// A call stack prefix. Node 0 is the top level.
+struct Node {
+    entry: usize,
+    parent: usize,
+    children: HashMap<usize, usize>,
+    samples: u64,
+}
+
+struct Frame {
+    node: usize,
+    /// The relative base before the callee's prologue, which its epilogue
+    /// restores.
+    base: i64,
+}
+
+#[derive(Default)]
+struct Record {
+    opcode: i64,
+    modes: [i64; 3],
+    values: [Option<i64>; 3],
+}
+
+/// An observer that counts executed instructions per pc and per opcode,
+/// finds loops by how often their back edges are taken, and attributes
+/// every instruction to a reconstructed call stack.
+///
+/// Calls are recognized by the calling convention of the puzzle programs:
+/// a jump straight into an `arb` with a positive operand is a call to the
+/// jump target, and an indirect jump right after an `arb` that restores a
+/// caller's relative base returns to that caller.
+pub struct Profiler {
+    hits: Vec<u64>,
+    words: HashMap<usize, Vec<i64>>,
+    opcodes: BTreeMap<i64, u64>,
+    back_edges: HashMap<(usize, usize), u64>,
+    calls: HashMap<usize, u64>,
+    nodes: Vec<Node>,
+    frames: Vec<Frame>,
+    relative_offset: i64,
+    record: Record,
+    /// A direct jump taken by the previous instruction, not yet known to
+    /// be a call or a back edge.
+    jumped: Option<(usize, usize)>,
+    /// Whether the previous instruction lowered the relative base.
+    released: bool,
+    steps: u64,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self {
+            hits: Vec::new(),
+            words: HashMap::new(),
+            opcodes: BTreeMap::new(),
+            back_edges: HashMap::new(),
+            calls: HashMap::new(),
+            nodes: vec![Node {
+                entry: 0,
+                parent: 0,
+                children: HashMap::new(),
+                samples: 0,
+            }],
+            frames: Vec::new(),
+            relative_offset: 0,
+            record: Record::default(),
+            jumped: None,
+            released: false,
+            steps: 0,
+        }
+    }
+
+    /// Number of executed instructions seen so far.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// How many times the instruction at `pc` executed.
+    pub fn hits(&self, pc: usize) -> u64 {
+        self.hits.get(pc).copied().unwrap_or(0)
+    }
+
+    /// Executions per opcode, keyed by opcode number.
+    pub fn opcodes(&self) -> &BTreeMap<i64, u64> {
+        &self.opcodes
+    }
+
+    /// Loops as `(start, end, iterations)`, where the jump at `end` went
+    /// back to `start`, most iterations first.
+    pub fn loops(&self) -> Vec<(usize, usize, u64)> {
+        let mut loops: Vec<_> = self
+            .back_edges
+            .iter()
+            .map(|(&(from, to), &count)| (to, from, count))
+            .collect();
+        loops.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)));
+        loops
+    }
+
+    /// The entry points of the calls currently on the stack, outermost
+    /// first.
+    pub fn stack(&self) -> Vec<usize> {
+        self.frames
+            .iter()
+            .map(|frame| self.nodes[frame.node].entry)
+            .collect()
+    }
+
+    fn current(&self) -> usize {
+        self.frames.last().map_or(0, |frame| frame.node)
+    }
+
+    fn call(&mut self, entry: usize, base: i64) {
+        let parent = self.current();
+        let next = self.nodes.len();
+        let node = *self.nodes[parent].children.entry(entry).or_insert(next);
+        if node == next {
+            self.nodes.push(Node {
+                entry,
+                parent,
+                children: HashMap::new(),
+                samples: 0,
+            });
+        }
+        self.frames.push(Frame { node, base });
+        *self.calls.entry(entry).or_insert(0) += 1;
+    }
+
+    /// Pops the frame whose caller's relative base is back in effect, and
+    /// any frames above it that returned without being noticed.
+    fn ret(&mut self) {
+        let base = self.relative_offset;
+        if let Some(i) = self.frames.iter().rposition(|frame| frame.base == base) {
+            self.frames.truncate(i);
+        }
+    }
+
+    fn label(&self, node: usize) -> String {
+        match node {
+            0 => "main".to_string(),
+            _ => format!("l{}", self.nodes[node].entry),
+        }
+    }
+
+    /// Writes one line per call stack with its instruction count, in the
+    /// folded format flame graph tools read: `main;l1378;l1263 1234`.
+    pub fn folded(&self, out: &mut impl Write) -> io::Result<()> {
+        for (id, node) in self.nodes.iter().enumerate() {
+            if node.samples == 0 {
+                continue;
+            }
+            let mut path = vec![self.label(id)];
+            let mut parent = id;
+            while parent != 0 {
+                parent = self.nodes[parent].parent;
+                path.push(self.label(parent));
+            }
+            path.reverse();
+            writeln!(out, "{} {}", path.join(";"), node.samples)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a summary: opcode mix, then the `top` hottest instructions,
+    /// loops and functions.
+    pub fn report(&self, out: &mut impl Write, top: usize) -> io::Result<()> {
+        let total = self.steps.max(1) as f64;
+        let percent = |count: u64| 100.0 * count as f64 / total;
+
+        writeln!(out, "{} instructions executed", self.steps)?;
+
+        writeln!(out, "\nopcodes:")?;
+        let mut opcodes: Vec<_> = self.opcodes.iter().collect();
+        opcodes.sort_by(|a, b| b.1.cmp(a.1));
+        for (&code, &count) in opcodes {
+            let mnemonic = Opcode::from_code(code).map_or("?", Opcode::mnemonic);
+            writeln!(
+                out,
+                "  {:<4} {:>12} {:>6.2}%",
+                mnemonic,
+                count,
+                percent(count)
+            )?;
+        }
+
+        writeln!(out, "\nhot instructions:")?;
+        let mut hot: Vec<_> = self
+            .hits
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > 0)
+            .collect();
+        hot.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(&b.0)));
+        for (pc, &count) in hot.into_iter().take(top) {
+            let text = self
+                .words
+                .get(&pc)
+                .and_then(|words| Instruction::decode(words, 0))
+                .map_or_else(|| "?".to_string(), |instr| instr.to_string());
+            writeln!(
+                out,
+                "  {:>6} {:>12} {:>6.2}%  {}",
+                pc,
+                count,
+                percent(count),
+                text
+            )?;
+        }
+
+        writeln!(out, "\nhot loops:")?;
+        for (start, end, count) in self.loops().into_iter().take(top) {
+            writeln!(out, "  {:>6}..={:<6} {:>12} iterations", start, end, count)?;
+        }
+
+        writeln!(out, "\nfunctions:")?;
+        let mut inclusive = vec![0; self.nodes.len()];
+        let mut by_entry: BTreeMap<usize, (u64, u64)> = BTreeMap::new();
+        for id in (1..self.nodes.len()).rev() {
+            let node = &self.nodes[id];
+            inclusive[id] += node.samples;
+            inclusive[node.parent] += inclusive[id];
+            let samples = by_entry.entry(node.entry).or_default();
+            samples.0 += node.samples;
+            // Recursion would count a call inside itself twice.
+            if !self.is_recursive(id) {
+                samples.1 += inclusive[id];
+            }
+        }
+        let mut functions: Vec<_> = by_entry.into_iter().collect();
+        functions.sort_by(|a, b| (b.1).1.cmp(&(a.1).1).then(a.0.cmp(&b.0)));
+        writeln!(
+            out,
+            "  {:>6} {:>10} {:>12} {:>12}",
+            "entry", "calls", "self", "total"
+        )?;
+        for (entry, (own, total)) in functions.into_iter().take(top) {
+            writeln!(
+                out,
+                "  {:>6} {:>10} {:>12} {:>12}",
+                format!("l{}", entry),
+                self.calls.get(&entry).copied().unwrap_or(0),
+                own,
+                total
+            )?;
+        }
+        Ok(())
+    }
+
+    fn is_recursive(&self, node: usize) -> bool {
+        let entry = self.nodes[node].entry;
+        let mut parent = self.nodes[node].parent;
+        while parent != 0 {
+            if self.nodes[parent].entry == entry {
+                return true;
+            }
+            parent = self.nodes[parent].parent;
+        }
+        false
+    }
+}
+
+impl<C: Cell> Observer<C> for Profiler {
+    fn instruction(&mut self, pc: usize, words: &[C]) {
+        let (opcode, modes) = decode(words[0].clamped());
+        self.record = Record {
+            opcode,
+            modes,
+            values: [None; 3],
+        };
+        if self.hits.get(pc).is_none_or(|&count| count == 0) {
+            self.words
+                .insert(pc, words.iter().map(Cell::clamped).collect());
+        }
+    }
+
+    fn param(&mut self, index: usize, value: C) {
+        self.record.values[index] = Some(value.clamped());
+    }
+
+    fn executed(&mut self, pc: usize) {
+        let r = &self.record;
+        let opcode = r.opcode;
+        let values = r.values;
+        let indirect = Mode::from_code(r.modes[1]) != Some(Mode::Immediate);
+
+        let mut released = false;
+        if opcode == 9 {
+            let value = values[0].unwrap_or(0);
+            let base = self.relative_offset;
+            self.relative_offset = base.saturating_add(value);
+            if value > 0 && self.jumped.is_some_and(|(_, to)| to == pc) {
+                self.jumped = None;
+                self.call(pc, base);
+            }
+            released = value < 0;
+        }
+        if let Some((from, to)) = self.jumped.take() {
+            if to <= from {
+                *self.back_edges.entry((from, to)).or_insert(0) += 1;
+            }
+        }
+
+        if self.hits.len() <= pc {
+            self.hits.resize(pc + 1, 0);
+        }
+        self.hits[pc] += 1;
+        *self.opcodes.entry(opcode).or_insert(0) += 1;
+        let node = self.current();
+        self.nodes[node].samples += 1;
+        self.steps += 1;
+
+        // A jump reads its target only when it is taken.
+        if let (5 | 6, Some(target)) = (opcode, values[1]) {
+            if indirect {
+                if self.released {
+                    self.ret();
+                }
+            } else if let Ok(target) = usize::try_from(target) {
+                self.jumped = Some((pc, target));
+            }
+        }
+        self.released = released;
+    }
+}
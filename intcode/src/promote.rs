@@ -0,0 +1,108 @@
+//! Running a program on `i64` cells until its arithmetic overflows, and on
+//! arbitrary-precision integers from then on.
+
+use crate::cpu::{Cpu, Machine, Status};
+use crate::error::{ErrorKind, IntcodeError};
+use crate::memory::{Dense, Memory};
+use crate::overflow::Overflow;
+use num_bigint::BigInt;
+use std::convert::TryFrom;
+
+/// A machine with the `Overflow::Promote` policy. It starts out as a
+/// regular `Cpu`; the first `add` or `mul` that overflows, or the first
+/// input too wide for an `i64`, moves the whole machine state onto big
+/// integers, where the instruction then runs again.
+#[derive(Clone)]
+pub struct Promoting {
+    stage: Stage,
+}
+
+#[derive(Clone)]
+enum Stage {
+    Narrow(Cpu),
+    Wide(Cpu<Dense<BigInt>>),
+}
+
+fn widen(cpu: &Cpu) -> Cpu<Dense<BigInt>> {
+    let cells = cpu.mem.cells();
+    let mut image = vec![BigInt::default(); cells.last().map_or(0, |&(addr, _)| addr + 1)];
+    for (addr, value) in cells {
+        image[addr] = value.into();
+    }
+    let mut wide = Cpu::with_memory(Dense::new(), &image);
+    wide.pc = cpu.pc;
+    wide.relative_offset = cpu.relative_offset.into();
+    wide.input = cpu.input.iter().map(|&value| value.into()).collect();
+    wide
+}
+
+impl Promoting {
+    pub fn new(program: &[i64]) -> Self {
+        let mut cpu = Cpu::new(program);
+        cpu.set_overflow(Overflow::Promote);
+        Self {
+            stage: Stage::Narrow(cpu),
+        }
+    }
+
+    fn promote(&mut self) {
+        if let Stage::Narrow(cpu) = &self.stage {
+            self.stage = Stage::Wide(widen(cpu));
+        }
+    }
+
+    pub fn push_input(&mut self, value: BigInt) {
+        if let Stage::Narrow(cpu) = &mut self.stage {
+            match i64::try_from(&value) {
+                Ok(value) => return cpu.push_input(value),
+                Err(_) => self.promote(),
+            }
+        }
+        if let Stage::Wide(cpu) = &mut self.stage {
+            cpu.push_input(value);
+        }
+    }
+
+    /// Every non-zero memory cell, in address order.
+    pub fn cells(&self) -> Vec<(usize, BigInt)> {
+        match &self.stage {
+            Stage::Narrow(cpu) => cpu
+                .mem
+                .cells()
+                .into_iter()
+                .map(|(addr, value)| (addr, value.into()))
+                .collect(),
+            Stage::Wide(cpu) => cpu.mem.cells(),
+        }
+    }
+
+    /// Whether the machine has moved onto big integers.
+    pub fn is_promoted(&self) -> bool {
+        matches!(self.stage, Stage::Wide(_))
+    }
+
+    pub fn run(&mut self) -> Result<Status<BigInt>, IntcodeError> {
+        loop {
+            match &mut self.stage {
+                Stage::Narrow(cpu) => match cpu.run() {
+                    Err(IntcodeError {
+                        kind: ErrorKind::Overflow(..),
+                        ..
+                    }) => self.promote(),
+                    result => return result.map(|status| status.map(BigInt::from)),
+                },
+                Stage::Wide(cpu) => return cpu.run(),
+            }
+        }
+    }
+}
+
+impl Machine<BigInt> for Promoting {
+    fn push_input(&mut self, value: BigInt) {
+        Promoting::push_input(self, value)
+    }
+
+    fn run(&mut self) -> Result<Status<BigInt>, IntcodeError> {
+        Promoting::run(self)
+    }
+}
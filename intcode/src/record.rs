@@ -0,0 +1,375 @@
+//! Recording a session with a CPU and replaying it.
+//!
+//! A `Recorder` runs a CPU and logs every value its program reads and
+//! writes, and every poke into its memory from outside, along with how many
+//! instructions had executed at the time. The log is a line-oriented text
+//! file:
+//!
+//! ```text
+//! intcode-log 1
+//! poke 0 0 2
+//! out 412 -1
+//! in 9043 0
+//! steps 10552
+//! ```
+//!
+//! `replay` runs the same program again on the logged input and checks that
+//! everything happens as logged.
+
+use crate::cpu::{Cpu, Machine, Status};
+use crate::error::IntcodeError;
+use crate::memory::{Dense, Memory};
+use crate::observer::Observer;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+pub const VERSION: u32 = 1;
+
+const MAGIC: &str = "intcode-log";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Entry {
+    /// The program read `value`.
+    In { step: u64, value: i64 },
+    /// The program wrote `value`.
+    Out { step: u64, value: i64 },
+    /// `value` was stored at `addr` from outside the program.
+    Poke { step: u64, addr: usize, value: i64 },
+}
+
+impl Entry {
+    /// Instructions executed before this entry.
+    pub fn step(&self) -> u64 {
+        match *self {
+            Entry::In { step, .. } | Entry::Out { step, .. } | Entry::Poke { step, .. } => step,
+        }
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Entry::In { step, value } => write!(f, "in {} {}", step, value),
+            Entry::Out { step, value } => write!(f, "out {} {}", step, value),
+            Entry::Poke { step, addr, value } => write!(f, "poke {} {} {}", step, addr, value),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Log {
+    pub entries: Vec<Entry>,
+    /// Instructions executed over the whole session.
+    pub steps: u64,
+}
+
+#[derive(Debug)]
+pub enum LogError {
+    Io(io::Error),
+    UnsupportedVersion(String),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for LogError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LogError::Io(e) => write!(f, "{}", e),
+            LogError::UnsupportedVersion(v) => write!(f, "unsupported log version: {}", v),
+            LogError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for LogError {}
+
+impl From<io::Error> for LogError {
+    fn from(e: io::Error) -> Self {
+        LogError::Io(e)
+    }
+}
+
+impl Log {
+    pub fn save(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "{} {}", MAGIC, VERSION)?;
+        for entry in &self.entries {
+            writeln!(out, "{}", entry)?;
+        }
+        writeln!(out, "steps {}", self.steps)
+    }
+
+    pub fn load(input: impl BufRead) -> Result<Self, LogError> {
+        let mut lines = input.lines();
+        let header = lines.next().transpose()?.unwrap_or_default();
+        match header.strip_prefix(MAGIC) {
+            Some(version) if version.trim() == VERSION.to_string() => {}
+            Some(version) => return Err(LogError::UnsupportedVersion(version.trim().to_string())),
+            None => {
+                return Err(LogError::Parse {
+                    line: 1,
+                    message: "not an intcode log".to_string(),
+                })
+            }
+        }
+
+        let mut log = Log::default();
+        for (i, line) in lines.enumerate() {
+            let line = line?;
+            let error = |message: &str| LogError::Parse {
+                line: i + 2,
+                message: message.to_string(),
+            };
+            let fields: Vec<_> = line.split_whitespace().collect();
+            let number = |index: usize| -> Result<i64, LogError> {
+                fields
+                    .get(index)
+                    .and_then(|field| field.parse().ok())
+                    .ok_or_else(|| error("invalid number"))
+            };
+            let step = || -> Result<u64, LogError> {
+                fields
+                    .get(1)
+                    .and_then(|field| field.parse().ok())
+                    .ok_or_else(|| error("invalid step"))
+            };
+            match fields.first().copied() {
+                None => continue,
+                Some("in") => log.entries.push(Entry::In {
+                    step: step()?,
+                    value: number(2)?,
+                }),
+                Some("out") => log.entries.push(Entry::Out {
+                    step: step()?,
+                    value: number(2)?,
+                }),
+                Some("poke") => log.entries.push(Entry::Poke {
+                    step: step()?,
+                    addr: fields
+                        .get(2)
+                        .and_then(|field| field.parse().ok())
+                        .ok_or_else(|| error("invalid address"))?,
+                    value: number(3)?,
+                }),
+                Some("steps") => log.steps = step()?,
+                Some(key) => return Err(error(&format!("unknown key {}", key))),
+            }
+        }
+        Ok(log)
+    }
+}
+
+/// Logs the reads and writes of the instructions it observes.
+#[derive(Default)]
+struct Logger {
+    log: Log,
+    opcode: i64,
+}
+
+impl Observer for Logger {
+    fn instruction(&mut self, _pc: usize, words: &[i64]) {
+        self.opcode = words[0] % 100;
+    }
+
+    fn param(&mut self, index: usize, value: i64) {
+        if self.opcode == 4 && index == 0 {
+            self.log.entries.push(Entry::Out {
+                step: self.log.steps,
+                value,
+            });
+        }
+    }
+
+    fn write(&mut self, _addr: usize, _old: i64, new: i64) {
+        if self.opcode == 3 {
+            self.log.entries.push(Entry::In {
+                step: self.log.steps,
+                value: new,
+            });
+        }
+    }
+
+    fn executed(&mut self, _pc: usize) {
+        self.log.steps += 1;
+    }
+}
+
+/// A CPU that logs its session. Memory changed through `poke` is logged
+/// too; changes through `cpu_mut` are not, and won't replay.
+pub struct Recorder<M: Memory<Cell = i64> = Dense> {
+    cpu: Cpu<M>,
+    logger: Logger,
+}
+
+impl<M: Memory<Cell = i64>> Recorder<M> {
+    pub fn new(cpu: Cpu<M>) -> Self {
+        Self {
+            cpu,
+            logger: Logger::default(),
+        }
+    }
+
+    pub fn cpu(&self) -> &Cpu<M> {
+        &self.cpu
+    }
+
+    pub fn cpu_mut(&mut self) -> &mut Cpu<M> {
+        &mut self.cpu
+    }
+
+    pub fn log(&self) -> &Log {
+        &self.logger.log
+    }
+
+    pub fn into_log(self) -> Log {
+        self.logger.log
+    }
+
+    /// Stores `value` at `addr`.
+    pub fn poke(&mut self, addr: usize, value: i64) {
+        let log = &mut self.logger.log;
+        log.entries.push(Entry::Poke {
+            step: log.steps,
+            addr,
+            value,
+        });
+        self.cpu.mem_mut()[addr] = value;
+    }
+
+    pub fn push_input(&mut self, value: i64) {
+        self.cpu.push_input(value);
+    }
+
+    pub fn run(&mut self) -> Result<Status, IntcodeError> {
+        self.cpu.run_with(&mut self.logger)
+    }
+
+    /// Like `run`, but returns `None` after `steps` instructions without a
+    /// status.
+    pub fn run_for(&mut self, steps: u64) -> Result<Option<Status>, IntcodeError> {
+        for _ in 0..steps {
+            if let Some(status) = self.cpu.step_with(&mut self.logger)? {
+                return Ok(Some(status));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<M: Memory<Cell = i64>> Machine for Recorder<M> {
+    fn push_input(&mut self, value: i64) {
+        Recorder::push_input(self, value)
+    }
+
+    fn run(&mut self) -> Result<Status, IntcodeError> {
+        Recorder::run(self)
+    }
+
+    fn run_for(&mut self, steps: u64) -> Result<Option<Status>, IntcodeError> {
+        Recorder::run_for(self, steps)
+    }
+}
+
+/// Why a replay didn't go as logged.
+#[derive(Debug)]
+pub enum ReplayError {
+    Intcode(IntcodeError),
+    /// Entry `index` of the replay differs from the log. `None` means one
+    /// of the two ended there.
+    Diverged {
+        index: usize,
+        expected: Option<Entry>,
+        found: Option<Entry>,
+    },
+    /// Everything was logged the same, but the session ran for a
+    /// different number of instructions.
+    Steps {
+        expected: u64,
+        found: u64,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let show =
+            |entry: &Option<Entry>| entry.map_or("end of log".to_string(), |e| e.to_string());
+        match self {
+            ReplayError::Intcode(e) => write!(f, "{}", e),
+            ReplayError::Diverged {
+                index,
+                expected,
+                found,
+            } => write!(
+                f,
+                "entry {}: expected {}, found {}",
+                index,
+                show(expected),
+                show(found)
+            ),
+            ReplayError::Steps { expected, found } => {
+                write!(f, "expected {} instructions, found {}", expected, found)
+            }
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+/// Runs `cpu`, which should be in the state the logged session started
+/// from, on the logged input and pokes, for as many instructions as the
+/// session ran. Fails at the first point where it doesn't do what the log
+/// says.
+pub fn replay<M: Memory<Cell = i64>>(cpu: Cpu<M>, log: &Log) -> Result<(), ReplayError> {
+    let mut recorder = Recorder::new(cpu);
+    for entry in &log.entries {
+        if let Entry::In { value, .. } = *entry {
+            recorder.push_input(value);
+        }
+    }
+    let mut pokes = log
+        .entries
+        .iter()
+        .filter_map(|entry| match *entry {
+            Entry::Poke { step, addr, value } => Some((step, addr, value)),
+            _ => None,
+        })
+        .peekable();
+
+    loop {
+        let steps = recorder.log().steps;
+        while let Some((_, addr, value)) = pokes.next_if(|&(step, _, _)| step == steps) {
+            recorder.poke(addr, value);
+        }
+        if steps >= log.steps {
+            break;
+        }
+        let status = recorder
+            .cpu
+            .step_with(&mut recorder.logger)
+            .map_err(ReplayError::Intcode)?;
+        if let Some(Status::NeedsInput | Status::Halted) = status {
+            break;
+        }
+    }
+
+    let found = recorder.log();
+    let index = log
+        .entries
+        .iter()
+        .zip(&found.entries)
+        .position(|(a, b)| a != b)
+        .unwrap_or_else(|| log.entries.len().min(found.entries.len()));
+    if index < log.entries.len().max(found.entries.len()) {
+        return Err(ReplayError::Diverged {
+            index,
+            expected: log.entries.get(index).copied(),
+            found: found.entries.get(index).copied(),
+        });
+    }
+    if found.steps != log.steps {
+        return Err(ReplayError::Steps {
+            expected: log.steps,
+            found: found.steps,
+        });
+    }
+    Ok(())
+}
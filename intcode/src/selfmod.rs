@@ -0,0 +1,68 @@
+use std::collections::HashSet;
+use std::fmt;
+
+/// Addresses below this are tracked in a bitmap, the rest in a set, so a
+/// jump far out into sparse memory doesn't allocate a bit for every word
+/// before it.
+const BITMAP_LIMIT: usize = 1 << 24;
+
+/// A store that changed a word some earlier instruction executed from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CodeWrite<C = i64> {
+    /// The instruction doing the store.
+    pub pc: usize,
+    pub addr: usize,
+    pub old: C,
+    pub new: C,
+}
+
+impl<C: fmt::Display> fmt::Display for CodeWrite<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "pc {} overwrote executed word at {}: {} -> {}",
+            self.pc, self.addr, self.old, self.new
+        )
+    }
+}
+
+/// Which addresses have been executed as part of an instruction, and the
+/// stores that later changed one of them.
+#[derive(Clone, Debug)]
+pub(crate) struct CodeTracker<C> {
+    executed: Vec<u64>,
+    far: HashSet<usize>,
+    pub(crate) writes: Vec<CodeWrite<C>>,
+}
+
+impl<C> CodeTracker<C> {
+    pub(crate) fn new() -> Self {
+        Self {
+            executed: Vec::new(),
+            far: HashSet::new(),
+            writes: Vec::new(),
+        }
+    }
+
+    pub(crate) fn execute(&mut self, pc: usize, size: usize) {
+        let end = pc.saturating_add(size);
+        if end > BITMAP_LIMIT {
+            self.far.extend(pc..end);
+            return;
+        }
+        let last = (end - 1) / 64;
+        if self.executed.len() <= last {
+            self.executed.resize(last + 1, 0);
+        }
+        for addr in pc..end {
+            self.executed[addr / 64] |= 1 << (addr % 64);
+        }
+    }
+
+    pub(crate) fn is_executed(&self, addr: usize) -> bool {
+        self.executed
+            .get(addr / 64)
+            .is_some_and(|&word| word & (1 << (addr % 64)) != 0)
+            || (!self.far.is_empty() && self.far.contains(&addr))
+    }
+}
@@ -0,0 +1,177 @@
+//! Saving and restoring CPU state.
+//!
+//! A snapshot is a line-oriented text file:
+//!
+//! ```text
+//! intcode-snapshot 1
+//! pc 1234
+//! relative_offset 5678
+//! input 110,111,114,116,104,10
+//! overflow trap
+//! mem 0 109,4814,21101,3124
+//! mem 4814 13
+//! ```
+//!
+//! `input` holds the values queued but not yet read, `overflow` the
+//! arithmetic policy (`trap` when absent), and each `mem` line a run of
+//! consecutive cells starting at the given address. Cells that are not
+//! listed are zero.
+
+use crate::cache::DecodeCache;
+use crate::cell::Cell;
+use crate::cpu::Cpu;
+use crate::memory::Memory;
+use crate::overflow::Overflow;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+pub const VERSION: u32 = 1;
+
+const MAGIC: &str = "intcode-snapshot";
+
+/// Short runs of zeros are written inline rather than starting a new line.
+const MAX_GAP: usize = 8;
+
+/// Restored memory has decodes cached up to its last cell, but no further
+/// than this.
+const MAX_CACHED: usize = 1 << 20;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    UnsupportedVersion(String),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Io(e) => write!(f, "{}", e),
+            SnapshotError::UnsupportedVersion(v) => {
+                write!(f, "unsupported snapshot version: {}", v)
+            }
+            SnapshotError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for SnapshotError {}
+
+impl From<io::Error> for SnapshotError {
+    fn from(e: io::Error) -> Self {
+        SnapshotError::Io(e)
+    }
+}
+
+fn join<C: Cell>(values: impl Iterator<Item = C>) -> String {
+    values.map(|v| v.to_string()).collect::<Vec<_>>().join(",")
+}
+
+/// Snapshots don't record the memory backend, so one saved from any
+/// backend can be restored into any other. Nor do they record the cell
+/// type; a value too wide for the restoring CPU's cells is a parse error.
+impl<M: Memory> Cpu<M> {
+    pub fn save(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "{} {}", MAGIC, VERSION)?;
+        writeln!(out, "pc {}", self.pc)?;
+        writeln!(out, "relative_offset {}", self.relative_offset)?;
+        writeln!(out, "input {}", join(self.input.iter().cloned()))?;
+        writeln!(out, "overflow {}", self.overflow)?;
+
+        let cells = self.mem.cells();
+        let mut run_start = 0;
+        for i in 1..=cells.len() {
+            if i == cells.len() || cells[i].0 - cells[i - 1].0 > MAX_GAP {
+                let start = cells[run_start].0;
+                let mut run = vec![M::Cell::default(); cells[i - 1].0 - start + 1];
+                for (addr, value) in &cells[run_start..i] {
+                    run[addr - start] = value.clone();
+                }
+                writeln!(out, "mem {} {}", start, join(run.into_iter()))?;
+                run_start = i;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn restore(input: impl BufRead) -> Result<Self, SnapshotError> {
+        let mut lines = input.lines();
+        let header = lines.next().transpose()?.unwrap_or_default();
+        match header.strip_prefix(MAGIC) {
+            Some(version) if version.trim() == VERSION.to_string() => {}
+            Some(version) => {
+                return Err(SnapshotError::UnsupportedVersion(
+                    version.trim().to_string(),
+                ))
+            }
+            None => {
+                return Err(SnapshotError::Parse {
+                    line: 1,
+                    message: "not an intcode snapshot".to_string(),
+                })
+            }
+        }
+
+        let mut len = 0;
+        let mut cpu = Self {
+            pc: 0,
+            relative_offset: M::Cell::default(),
+            mem: M::default(),
+            input: VecDeque::new(),
+            cache: DecodeCache::new(0),
+            overflow: Overflow::default(),
+            code: None,
+        };
+
+        for (i, line) in lines.enumerate() {
+            let line = line?;
+            let error = |message: &str| SnapshotError::Parse {
+                line: i + 2,
+                message: message.to_string(),
+            };
+            let parse_list = |s: &str| -> Result<Vec<M::Cell>, SnapshotError> {
+                s.split(',')
+                    .filter(|v| !v.is_empty())
+                    .map(|v| v.parse().map_err(|_| error("invalid value")))
+                    .collect()
+            };
+
+            let mut fields = line.splitn(3, ' ');
+            match (fields.next(), fields.next(), fields.next()) {
+                (Some(""), None, None) => {}
+                (Some("pc"), Some(pc), None) => {
+                    cpu.pc = pc.parse().map_err(|_| error("invalid pc"))?;
+                }
+                (Some("relative_offset"), Some(offset), None) => {
+                    cpu.relative_offset = offset
+                        .parse()
+                        .map_err(|_| error("invalid relative offset"))?;
+                }
+                (Some("input"), values, None) => {
+                    cpu.input = parse_list(values.unwrap_or(""))?.into();
+                }
+                (Some("overflow"), Some(policy), None) => {
+                    cpu.overflow = policy.parse().map_err(|e: String| error(&e))?;
+                }
+                (Some("mem"), Some(addr), Some(values)) => {
+                    let addr: usize = addr.parse().map_err(|_| error("invalid address"))?;
+                    let values = parse_list(values)?;
+                    let end = addr
+                        .checked_add(values.len())
+                        .filter(|&end| end == addr || cpu.mem.fits(end - 1))
+                        .ok_or_else(|| error("address out of range"))?;
+                    for (offset, value) in values.into_iter().enumerate() {
+                        cpu.mem[addr + offset] = value;
+                    }
+                    len = len.max(end);
+                }
+                _ => return Err(error("unrecognized line")),
+            }
+        }
+
+        cpu.cache = DecodeCache::new(len.min(MAX_CACHED));
+        Ok(cpu)
+    }
+}
@@ -0,0 +1,480 @@
+//! Symbolic execution of position-mode `add`/`mul` programs like day 2's,
+//! with some of the initial cells left unknown. Every cell ends up as an
+//! expression over those unknowns, which can then be solved for a value.
+
+use crate::memory::Dense;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+#[derive(Clone, Copy, Debug)]
+enum Node {
+    Const(i64),
+    /// The unknown at this index of the symbol list.
+    Sym(usize),
+    Add(usize, usize),
+    Mul(usize, usize),
+    /// The cell at a symbolic address, in memory as it was at the read.
+    Load {
+        addr: usize,
+        snapshot: usize,
+    },
+}
+
+const ZERO: usize = 0;
+
+#[derive(Debug)]
+pub enum SymbolicError {
+    /// The instruction at `pc` isn't a position-mode `add`, `mul` or
+    /// `halt`.
+    Unsupported {
+        pc: usize,
+        opcode: i64,
+    },
+    /// The opcode at `pc` depends on the unknowns, and so does everything
+    /// the program does from there.
+    SymbolicControl {
+        pc: usize,
+    },
+    /// The instruction at `pc` writes to an address that depends on the
+    /// unknowns.
+    SymbolicWrite {
+        pc: usize,
+    },
+    /// The instruction at `pc` reads from a negative address, or stores
+    /// past what `Dense` memory holds. An unknown placed past that limit is
+    /// reported at pc 0.
+    InvalidAddress {
+        pc: usize,
+        addr: i64,
+    },
+    Overflow {
+        pc: usize,
+    },
+}
+
+impl fmt::Display for SymbolicError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SymbolicError::Unsupported { pc, opcode } => {
+                write!(f, "unsupported opcode {} at {}", opcode, pc)
+            }
+            SymbolicError::SymbolicControl { pc } => {
+                write!(f, "control flow depends on the unknowns at {}", pc)
+            }
+            SymbolicError::SymbolicWrite { pc } => {
+                write!(f, "write address depends on the unknowns at {}", pc)
+            }
+            SymbolicError::InvalidAddress { pc, addr } => {
+                write!(f, "invalid address {} at {}", addr, pc)
+            }
+            SymbolicError::Overflow { pc } => write!(f, "arithmetic overflow at {}", pc),
+        }
+    }
+}
+
+impl Error for SymbolicError {}
+
+/// Memory once a program has halted. The cells are nodes of one graph,
+/// so that the expressions they share are only built once.
+pub struct Execution {
+    symbols: Vec<usize>,
+    nodes: Vec<Node>,
+    snapshots: Vec<Vec<usize>>,
+    mem: Vec<usize>,
+    /// Whether `mem` changed since the last snapshot.
+    dirty: bool,
+}
+
+impl Execution {
+    /// Runs `program` with the cells at `symbols` unknown, until it halts.
+    pub fn run(program: &[i64], symbols: &[usize]) -> Result<Self, SymbolicError> {
+        let mut exec = Self {
+            symbols: symbols.to_vec(),
+            nodes: vec![Node::Const(0)],
+            snapshots: Vec::new(),
+            mem: vec![ZERO; program.len()],
+            dirty: true,
+        };
+        for (addr, &value) in program.iter().enumerate() {
+            let node = exec.constant(value);
+            exec.store(0, addr, node)?;
+        }
+        for (index, &addr) in symbols.iter().enumerate() {
+            let node = exec.push(Node::Sym(index));
+            exec.store(0, addr, node)?;
+        }
+
+        let mut pc = 0;
+        loop {
+            let opcode = match exec.nodes[exec.cell(pc)] {
+                Node::Const(opcode) => opcode,
+                _ => return Err(SymbolicError::SymbolicControl { pc }),
+            };
+            let (a, b) = match opcode {
+                1 | 2 => (exec.read(pc, pc + 1)?, exec.read(pc, pc + 2)?),
+                99 => return Ok(exec),
+                _ => return Err(SymbolicError::Unsupported { pc, opcode }),
+            };
+            let dst = match exec.nodes[exec.cell(pc + 3)] {
+                Node::Const(addr) => address(pc, addr)?,
+                _ => return Err(SymbolicError::SymbolicWrite { pc }),
+            };
+            let value = if opcode == 1 {
+                exec.add(pc, a, b)?
+            } else {
+                exec.mul(pc, a, b)?
+            };
+            exec.store(pc, dst, value)?;
+            pc += 4;
+        }
+    }
+
+    /// The addresses of the unknowns, in the order `Formula` takes their
+    /// values.
+    pub fn symbols(&self) -> &[usize] {
+        &self.symbols
+    }
+
+    fn cell(&self, addr: usize) -> usize {
+        self.mem.get(addr).copied().unwrap_or(ZERO)
+    }
+
+    /// The value of the cell at `addr`.
+    pub fn formula(&self, addr: usize) -> Formula<'_> {
+        Formula {
+            exec: self,
+            node: self.cell(addr),
+        }
+    }
+
+    fn push(&mut self, node: Node) -> usize {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    fn constant(&mut self, value: i64) -> usize {
+        if value == 0 {
+            ZERO
+        } else {
+            self.push(Node::Const(value))
+        }
+    }
+
+    fn store(&mut self, pc: usize, addr: usize, node: usize) -> Result<(), SymbolicError> {
+        if addr >= self.mem.len() {
+            if addr >= Dense::<i64>::DEFAULT_LIMIT {
+                let addr = i64::try_from(addr).unwrap_or(i64::MAX);
+                return Err(SymbolicError::InvalidAddress { pc, addr });
+            }
+            self.mem.resize(addr + 1, ZERO);
+        }
+        self.mem[addr] = node;
+        self.dirty = true;
+        Ok(())
+    }
+
+    /// The cell that the parameter at `param` points to.
+    fn read(&mut self, pc: usize, param: usize) -> Result<usize, SymbolicError> {
+        let addr = self.cell(param);
+        if let Node::Const(addr) = self.nodes[addr] {
+            return Ok(self.cell(address(pc, addr)?));
+        }
+        if self.dirty {
+            self.snapshots.push(self.mem.clone());
+            self.dirty = false;
+        }
+        let snapshot = self.snapshots.len() - 1;
+        Ok(self.push(Node::Load { addr, snapshot }))
+    }
+
+    fn add(&mut self, pc: usize, a: usize, b: usize) -> Result<usize, SymbolicError> {
+        Ok(match (self.nodes[a], self.nodes[b]) {
+            (Node::Const(a), Node::Const(b)) => {
+                let sum = a.checked_add(b).ok_or(SymbolicError::Overflow { pc })?;
+                self.constant(sum)
+            }
+            (Node::Const(0), _) => b,
+            (_, Node::Const(0)) => a,
+            _ => self.push(Node::Add(a, b)),
+        })
+    }
+
+    fn mul(&mut self, pc: usize, a: usize, b: usize) -> Result<usize, SymbolicError> {
+        Ok(match (self.nodes[a], self.nodes[b]) {
+            (Node::Const(a), Node::Const(b)) => {
+                let product = a.checked_mul(b).ok_or(SymbolicError::Overflow { pc })?;
+                self.constant(product)
+            }
+            (Node::Const(0), _) | (_, Node::Const(0)) => ZERO,
+            (Node::Const(1), _) => b,
+            (_, Node::Const(1)) => a,
+            _ => self.push(Node::Mul(a, b)),
+        })
+    }
+}
+
+fn address(pc: usize, addr: i64) -> Result<usize, SymbolicError> {
+    if addr < 0 {
+        return Err(SymbolicError::InvalidAddress { pc, addr });
+    }
+    Ok(addr as usize)
+}
+
+/// A polynomial over the unknowns, from each monomial's exponents to its
+/// coefficient. Zero terms are left out.
+type Poly = BTreeMap<Vec<u32>, i64>;
+
+fn poly_add(a: &Poly, b: &Poly) -> Option<Poly> {
+    let mut sum = a.clone();
+    for (monomial, &coefficient) in b {
+        let term = sum.entry(monomial.clone()).or_insert(0);
+        *term = term.checked_add(coefficient)?;
+        if *term == 0 {
+            sum.remove(monomial);
+        }
+    }
+    Some(sum)
+}
+
+fn poly_mul(a: &Poly, b: &Poly) -> Option<Poly> {
+    let mut product = Poly::new();
+    for (x, &p) in a {
+        for (y, &q) in b {
+            let monomial = x
+                .iter()
+                .zip(y)
+                .map(|(i, j)| i.checked_add(*j))
+                .collect::<Option<Vec<_>>>()?;
+            let term = product.entry(monomial).or_insert(0);
+            *term = term.checked_add(p.checked_mul(q)?)?;
+        }
+    }
+    product.retain(|_, term| *term != 0);
+    Some(product)
+}
+
+/// An expression for one cell.
+#[derive(Clone, Copy)]
+pub struct Formula<'a> {
+    exec: &'a Execution,
+    node: usize,
+}
+
+impl Formula<'_> {
+    /// The value for the given values of the unknowns, or `None` if the
+    /// program faults with them or there are fewer values than unknowns.
+    pub fn eval(&self, values: &[i64]) -> Option<i64> {
+        let exec = self.exec;
+        let mut results: Vec<Option<i64>> = Vec::with_capacity(self.node + 1);
+        for node in &exec.nodes[..=self.node] {
+            let result = match *node {
+                Node::Const(value) => Some(value),
+                Node::Sym(index) => values.get(index).copied(),
+                Node::Add(a, b) => results[a].and_then(|a| a.checked_add(results[b]?)),
+                Node::Mul(a, b) => results[a].and_then(|a| a.checked_mul(results[b]?)),
+                Node::Load { addr, snapshot } => {
+                    results[addr].filter(|&addr| addr >= 0).and_then(|addr| {
+                        let cell = exec.snapshots[snapshot].get(addr as usize);
+                        results[*cell.unwrap_or(&ZERO)]
+                    })
+                }
+            };
+            results.push(result);
+        }
+        results[self.node]
+    }
+
+    /// The formula as a polynomial, unless it reads through an address
+    /// that depends on the unknowns or its coefficients overflow.
+    fn poly(&self) -> Option<Poly> {
+        let exec = self.exec;
+        let constant = |value: i64| {
+            let mut poly = Poly::new();
+            if value != 0 {
+                poly.insert(vec![0; exec.symbols.len()], value);
+            }
+            poly
+        };
+        let mut polys: Vec<Option<Poly>> = Vec::with_capacity(self.node + 1);
+        for node in &exec.nodes[..=self.node] {
+            let poly = match *node {
+                Node::Const(value) => Some(constant(value)),
+                Node::Sym(index) => {
+                    let mut monomial = vec![0; exec.symbols.len()];
+                    monomial[index] = 1;
+                    Some(vec![(monomial, 1)].into_iter().collect())
+                }
+                Node::Add(a, b) => match (&polys[a], &polys[b]) {
+                    (Some(a), Some(b)) => poly_add(a, b),
+                    _ => None,
+                },
+                Node::Mul(a, b) => match (&polys[a], &polys[b]) {
+                    (Some(a), Some(b)) => poly_mul(a, b),
+                    _ => None,
+                },
+                Node::Load { .. } => None,
+            };
+            polys.push(poly);
+        }
+        polys.pop().flatten()
+    }
+
+    /// Whether the formula is a polynomial of degree one at most.
+    pub fn is_linear(&self) -> bool {
+        self.poly().as_ref().is_some_and(is_linear)
+    }
+
+    /// Every assignment of values in `domain` to the unknowns for which
+    /// the formula is `target`, in lexicographic order. A linear formula
+    /// is solved for its last unknown; anything else is evaluated on the
+    /// whole domain.
+    pub fn solve(&self, target: i64, domain: RangeInclusive<i64>) -> Vec<Vec<i64>> {
+        let count = self.exec.symbols.len();
+        let linear = self.poly().filter(is_linear);
+        let (poly, pivot) = match linear.as_ref().map(|poly| (poly, pivot(poly))) {
+            Some((poly, Some(pivot))) => (poly, pivot),
+            _ => {
+                return assignments(count, &domain)
+                    .filter(|values| self.eval(values) == Some(target))
+                    .collect()
+            }
+        };
+
+        // target = constant + sum(coefficient * value), for the pivot's
+        // value.
+        let coefficient = |index: usize| {
+            let mut monomial = vec![0; count];
+            monomial[index] = 1;
+            poly.get(&monomial).copied().unwrap_or(0)
+        };
+        let divisor = coefficient(pivot);
+        let constant = poly.get(&vec![0; count]).copied().unwrap_or(0);
+        let mut solutions = Vec::new();
+        for mut values in assignments(count - 1, &domain) {
+            values.insert(pivot, 0);
+            let rest = (0..count).filter(|&index| index != pivot).try_fold(
+                i128::from(constant),
+                |sum, index| {
+                    sum.checked_add(i128::from(coefficient(index)) * i128::from(values[index]))
+                },
+            );
+            let remainder = match rest {
+                Some(rest) => i128::from(target) - rest,
+                None => continue,
+            };
+            if remainder % i128::from(divisor) != 0 {
+                continue;
+            }
+            let value = remainder / i128::from(divisor);
+            if value >= i128::from(*domain.start()) && value <= i128::from(*domain.end()) {
+                values[pivot] = value as i64;
+                solutions.push(values);
+            }
+        }
+        solutions
+    }
+}
+
+fn is_linear(poly: &Poly) -> bool {
+    poly.keys()
+        .all(|monomial| monomial.iter().sum::<u32>() <= 1)
+}
+
+/// The last unknown a linear polynomial depends on.
+fn pivot(poly: &Poly) -> Option<usize> {
+    poly.keys()
+        .filter_map(|monomial| monomial.iter().position(|&exponent| exponent == 1))
+        .max()
+}
+
+/// Every assignment of values in `domain` to `count` unknowns, in
+/// lexicographic order.
+fn assignments(count: usize, domain: &RangeInclusive<i64>) -> impl Iterator<Item = Vec<i64>> {
+    let (start, end) = (*domain.start(), *domain.end());
+    let mut next = if start <= end {
+        Some(vec![start; count])
+    } else {
+        None
+    };
+    std::iter::from_fn(move || {
+        let values = next.take()?;
+        let mut successor = values.clone();
+        for value in successor.iter_mut().rev() {
+            if *value < end {
+                *value += 1;
+                next = Some(successor);
+                break;
+            }
+            *value = start;
+        }
+        Some(values)
+    })
+}
+
+impl fmt::Display for Formula<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbols = &self.exec.symbols;
+        let poly = match self.poly() {
+            Some(poly) => poly,
+            None => return write_node(f, self.exec, self.node),
+        };
+        if poly.is_empty() {
+            return write!(f, "0");
+        }
+        for (i, (monomial, &coefficient)) in poly.iter().rev().enumerate() {
+            let constant = monomial.iter().all(|&exponent| exponent == 0);
+            match (i, coefficient < 0) {
+                (0, true) => write!(f, "-")?,
+                (0, false) => {}
+                (_, true) => write!(f, " - ")?,
+                (_, false) => write!(f, " + ")?,
+            }
+            let magnitude = coefficient.unsigned_abs();
+            if constant || magnitude != 1 {
+                write!(f, "{}", magnitude)?;
+            }
+            let mut first = constant || magnitude == 1;
+            for (&addr, &exponent) in symbols.iter().zip(monomial) {
+                if exponent == 0 {
+                    continue;
+                }
+                if !first {
+                    write!(f, "*")?;
+                }
+                first = false;
+                write!(f, "prog[{}]", addr)?;
+                if exponent > 1 {
+                    write!(f, "^{}", exponent)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_node(f: &mut fmt::Formatter, exec: &Execution, node: usize) -> fmt::Result {
+    match exec.nodes[node] {
+        Node::Const(value) => write!(f, "{}", value),
+        Node::Sym(index) => write!(f, "prog[{}]", exec.symbols[index]),
+        Node::Add(a, b) => {
+            write!(f, "(")?;
+            write_node(f, exec, a)?;
+            write!(f, " + ")?;
+            write_node(f, exec, b)?;
+            write!(f, ")")
+        }
+        Node::Mul(a, b) => {
+            write_node(f, exec, a)?;
+            write!(f, "*")?;
+            write_node(f, exec, b)
+        }
+        Node::Load { addr, .. } => {
+            write!(f, "mem[")?;
+            write_node(f, exec, addr)?;
+            write!(f, "]")
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use crate::observer::Observer;
+use crate::opcode::{Opcode, OPCODES};
+use std::io::{self, Write};
+use std::ops::Range;
+
+/// Selects which executed instructions a `Tracer` records. Every filter
+/// that is set must match.
+#[derive(Clone, Debug, Default)]
+pub struct TraceFilter {
+    pub pc: Option<Range<usize>>,
+    pub opcodes: Option<Vec<Opcode>>,
+    /// Window of instruction counts, starting at 0 for the first
+    /// instruction the tracer sees executed.
+    pub steps: Option<Range<u64>>,
+}
+
+impl TraceFilter {
+    fn matches(&self, step: u64, pc: usize, opcode: i64) -> bool {
+        self.pc.as_ref().is_none_or(|r| r.contains(&pc))
+            && self.steps.as_ref().is_none_or(|r| r.contains(&step))
+            && self
+                .opcodes
+                .as_ref()
+                .is_none_or(|ops| ops.iter().any(|op| op.code() == opcode))
+    }
+}
+
+#[derive(Default)]
+struct Record {
+    pc: usize,
+    words: Vec<i64>,
+    values: Vec<Option<i64>>,
+    write: Option<(usize, i64, i64)>,
+}
+
+/// An observer that streams one JSON object per executed instruction:
+///
+/// ```text
+/// {"step":0,"pc":0,"opcode":1,"mnemonic":"add","operands":[4,5,6],"values":[2,3,null],"write":{"addr":6,"old":0,"new":5}}
+/// ```
+///
+/// `operands` are the raw parameter words and `values` what each one
+/// resolved to after mode handling; the written parameter and any jump
+/// target a branch didn't read are `null`.
+pub struct Tracer<W: Write> {
+    out: W,
+    filter: TraceFilter,
+    step: u64,
+    record: Record,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> Tracer<W> {
+    pub fn new(out: W, filter: TraceFilter) -> Self {
+        Self {
+            out,
+            filter,
+            step: 0,
+            record: Record::default(),
+            error: None,
+        }
+    }
+
+    /// Number of executed instructions seen so far.
+    pub fn steps(&self) -> u64 {
+        self.step
+    }
+
+    /// Returns the writer, or the first error writing to it.
+    pub fn finish(self) -> io::Result<W> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.out),
+        }
+    }
+
+    fn write_record(&mut self) -> io::Result<()> {
+        let r = &self.record;
+        let opcode = r.words[0] % 100;
+        let mnemonic = OPCODES
+            .iter()
+            .find(|op| op.code() == opcode)
+            .map_or("?", |op| op.mnemonic());
+        let list = |values: &mut dyn Iterator<Item = String>| values.collect::<Vec<_>>().join(",");
+
+        write!(
+            self.out,
+            "{{\"step\":{},\"pc\":{},\"opcode\":{},\"mnemonic\":\"{}\",\"operands\":[{}],\"values\":[{}]",
+            self.step,
+            r.pc,
+            opcode,
+            mnemonic,
+            list(&mut r.words[1..].iter().map(|w| w.to_string())),
+            list(&mut r.values.iter().map(|v| match v {
+                Some(v) => v.to_string(),
+                None => "null".to_string(),
+            })),
+        )?;
+        if let Some((addr, old, new)) = r.write {
+            write!(
+                self.out,
+                ",\"write\":{{\"addr\":{},\"old\":{},\"new\":{}}}",
+                addr, old, new
+            )?;
+        }
+        writeln!(self.out, "}}")
+    }
+}
+
+impl<W: Write> Observer for Tracer<W> {
+    fn instruction(&mut self, pc: usize, words: &[i64]) {
+        self.record = Record {
+            pc,
+            words: words.to_vec(),
+            values: vec![None; words.len() - 1],
+            write: None,
+        };
+    }
+
+    fn param(&mut self, index: usize, value: i64) {
+        self.record.values[index] = Some(value);
+    }
+
+    fn write(&mut self, addr: usize, old: i64, new: i64) {
+        self.record.write = Some((addr, old, new));
+    }
+
+    fn executed(&mut self, pc: usize) {
+        if self.error.is_none()
+            && self
+                .filter
+                .matches(self.step, pc, self.record.words[0] % 100)
+        {
+            if let Err(e) = self.write_record() {
+                self.error = Some(e);
+            }
+        }
+        self.step += 1;
+    }
+}
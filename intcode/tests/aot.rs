@@ -0,0 +1,52 @@
+//! Transpiled programs, checked in under `aot/` and kept up to date with
+//! `transpile` by the tests here.
+
+use intcode::{aot, Cpu, Machine};
+
+#[allow(clippy::all, unused)]
+mod far_store {
+    include!("aot/far_store.rs");
+}
+
+#[allow(clippy::all, unused)]
+mod far_relative {
+    include!("aot/far_relative.rs");
+}
+
+#[allow(clippy::all, unused)]
+mod spin {
+    include!("aot/spin.rs");
+}
+
+#[test]
+fn generated_modules_are_current() {
+    let modules = [
+        (far_store::PROGRAM, include_str!("aot/far_store.rs")),
+        (far_relative::PROGRAM, include_str!("aot/far_relative.rs")),
+        (spin::PROGRAM, include_str!("aot/spin.rs")),
+    ];
+    for (program, source) in &modules {
+        assert_eq!(aot::transpile(program), *source);
+    }
+}
+
+/// Stores past what memory holds fault like they do in the interpreter.
+#[test]
+fn stores_past_memory_fault() {
+    let cases = [
+        (far_store::PROGRAM, far_store::new()),
+        (far_relative::PROGRAM, far_relative::new()),
+    ];
+    for (program, mut native) in cases {
+        let expected = Cpu::new(program).run();
+        assert!(expected.is_err());
+        assert_eq!(native.run(), expected);
+    }
+}
+
+#[test]
+fn runs_for_a_bounded_number_of_steps() {
+    let mut native = spin::new();
+    assert_eq!(native.run_for(1000), Ok(None));
+    assert_eq!(native.cpu().pc(), 0);
+}
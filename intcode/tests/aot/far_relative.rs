@@ -0,0 +1,39 @@
+// Generated by intcode::aot::transpile from a 7-word program.
+
+use intcode::aot::Native;
+use intcode::Status;
+
+pub const PROGRAM: &[i64] = &[
+    109, 19999999, 21101, 1, 1, 1, 99,
+];
+
+const CODE: &[(usize, usize)] = &[
+    (0, 7),
+];
+
+pub fn new() -> Native {
+    Native::new(PROGRAM, CODE, run)
+}
+
+fn run(m: &mut Native) -> Option<Status> {
+    loop {
+        match m.fetch() {
+            0 => {
+                let a = 19999999;
+                m.arb(a)?;
+                m.goto(2);
+            }
+            2 => {
+                let a: i64 = 1;
+                let b = 1;
+                let d = m.rel(1)?;
+                m.set(d, a.checked_add(b)?);
+                m.goto(6);
+            }
+            6 => {
+                return Some(Status::Halted);
+            }
+            _ => return None,
+        }
+    }
+}
@@ -0,0 +1,39 @@
+// Generated by intcode::aot::transpile from a 7-word program.
+
+use intcode::aot::Native;
+use intcode::Status;
+
+pub const PROGRAM: &[i64] = &[
+    1101, 1, 1, 20000000, 4, 0, 99,
+];
+
+const CODE: &[(usize, usize)] = &[
+    (0, 7),
+];
+
+pub fn new() -> Native {
+    Native::new(PROGRAM, CODE, run)
+}
+
+fn run(m: &mut Native) -> Option<Status> {
+    loop {
+        match m.fetch() {
+            0 => {
+                let a: i64 = 1;
+                let b = 1;
+                let d = m.pos(20000000)?;
+                m.set(d, a.checked_add(b)?);
+                m.goto(4);
+            }
+            4 => {
+                let a = m.get(0);
+                m.goto(6);
+                return Some(Status::Output(a));
+            }
+            6 => {
+                return Some(Status::Halted);
+            }
+            _ => return None,
+        }
+    }
+}
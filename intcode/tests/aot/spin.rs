@@ -0,0 +1,31 @@
+// Generated by intcode::aot::transpile from a 3-word program.
+
+use intcode::aot::Native;
+use intcode::Status;
+
+pub const PROGRAM: &[i64] = &[
+    1105, 1, 0,
+];
+
+const CODE: &[(usize, usize)] = &[
+    (0, 3),
+];
+
+pub fn new() -> Native {
+    Native::new(PROGRAM, CODE, run)
+}
+
+fn run(m: &mut Native) -> Option<Status> {
+    loop {
+        match m.fetch() {
+            0 => {
+                if 1 != 0 {
+                    m.goto(0);
+                } else {
+                    m.goto(3);
+                }
+            }
+            _ => return None,
+        }
+    }
+}
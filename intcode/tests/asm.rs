@@ -0,0 +1,58 @@
+//! Assembling source back into the programs it was disassembled from.
+
+use intcode::asm::{self, AsmErrorKind};
+use intcode::{disasm, Cpu, Status};
+
+/// The Intcode puzzle inputs.
+const INPUTS: &[&str] = &[
+    include_str!("../../day02/input"),
+    include_str!("../../day05/input"),
+    include_str!("../../day07/input"),
+    include_str!("../../day09/input"),
+    include_str!("../../day11/input"),
+    include_str!("../../day13/input"),
+    include_str!("../../day15/input"),
+    include_str!("../../day17/input"),
+    include_str!("../../day19/input"),
+    include_str!("../../day21/input"),
+    include_str!("../../day23/input"),
+    include_str!("../../day25/input"),
+];
+
+#[test]
+fn disassembly_assembles_back_to_the_program() {
+    for input in INPUTS {
+        let program: Vec<i64> = intcode::parse_program(input).unwrap();
+        let source = disasm::disassemble(&program).to_string();
+        assert_eq!(asm::assemble(&source).unwrap(), program);
+    }
+}
+
+#[test]
+fn assembled_programs_run() {
+    let source = "
+        ; Outputs the numbers from 3 down to 1.
+        loop:   out [n]
+                add [n], #-1, [n]
+                jnz [n], #loop
+                hlt
+        n:      .data 3
+    ";
+    let mut cpu = Cpu::new(&asm::assemble(source).unwrap());
+    let mut outputs = Vec::new();
+    while let Status::Output(value) = cpu.run().unwrap() {
+        outputs.push(value);
+    }
+    assert_eq!(outputs, [3, 2, 1]);
+}
+
+#[test]
+fn reports_errors_by_line() {
+    let err = asm::assemble("hlt\nadd [1], [2], #3\n").unwrap_err();
+    assert_eq!(
+        (err.line, err.kind),
+        (2, AsmErrorKind::ImmediateDestination)
+    );
+    let err = asm::assemble("jz [0], #nowhere").unwrap_err();
+    assert_eq!(err.kind, AsmErrorKind::UndefinedLabel("nowhere".into()));
+}
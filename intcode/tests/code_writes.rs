@@ -0,0 +1,44 @@
+//! Stores into words that already ran as code.
+
+use intcode::{CodeWrite, Cpu, Sparse, Status};
+
+#[test]
+fn reports_stores_into_executed_words() {
+    // 1101,3,4,1 overwrites its own first operand with 7.
+    let mut cpu = Cpu::new(&[1101, 3, 4, 1, 99]);
+    cpu.detect_code_writes(true);
+    assert_eq!(cpu.run(), Ok(Status::Halted));
+    let write = CodeWrite {
+        pc: 0,
+        addr: 1,
+        old: 3,
+        new: 7,
+    };
+    assert_eq!(cpu.code_writes(), [write]);
+    assert_eq!(cpu.take_code_writes(), [write]);
+    assert!(cpu.code_writes().is_empty());
+}
+
+/// Code far out in sparse memory is tracked without a bit for every word
+/// before it.
+#[test]
+fn tracks_code_far_out_in_memory() {
+    const FAR: i64 = 100_000_000_000;
+    let far = [1101, 7, 1, FAR + 1, 99];
+    // Stores `far` out there word by word, then jumps to it.
+    let mut program: Vec<_> = (FAR..)
+        .zip(&far)
+        .flat_map(|(addr, &value)| vec![1101, 0, value, addr])
+        .collect();
+    program.extend(&[1105, 1, FAR]);
+    let mut cpu = Cpu::with_memory(Sparse::new(), &program);
+    cpu.detect_code_writes(true);
+    assert_eq!(cpu.run(), Ok(Status::Halted));
+    let write = CodeWrite {
+        pc: FAR as usize,
+        addr: FAR as usize + 1,
+        old: 7,
+        new: 8,
+    };
+    assert_eq!(cpu.code_writes(), [write]);
+}
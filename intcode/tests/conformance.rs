@@ -0,0 +1,279 @@
+//! The example programs from the puzzle texts of days 2, 5 and 9, run on
+//! every engine, memory backend and cell type. A cell type only runs the
+//! examples whose program, input and expected values it can hold.
+
+use intcode::{BigInt, Cell, Cpu, Dense, Hybrid, IntcodeError, Machine, Memory, Sparse, Status};
+use std::any;
+
+struct Case {
+    name: &'static str,
+    program: &'static [i64],
+    input: &'static [i64],
+    output: &'static [i64],
+    /// The start of memory once the program halts; empty if unchecked.
+    memory: &'static [i64],
+}
+
+const fn run(
+    name: &'static str,
+    program: &'static [i64],
+    input: &'static [i64],
+    output: &'static [i64],
+) -> Case {
+    Case {
+        name,
+        program,
+        input,
+        output,
+        memory: &[],
+    }
+}
+
+const fn halt(name: &'static str, program: &'static [i64], memory: &'static [i64]) -> Case {
+    Case {
+        name,
+        program,
+        input: &[],
+        output: &[],
+        memory,
+    }
+}
+
+const DAY02: &[Case] = &[
+    halt(
+        "add and multiply",
+        &[1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50],
+        &[3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50],
+    ),
+    halt("add", &[1, 0, 0, 0, 99], &[2, 0, 0, 0, 99]),
+    halt("multiply", &[2, 3, 0, 3, 99], &[2, 3, 0, 6, 99]),
+    halt(
+        "multiply past the halt",
+        &[2, 4, 4, 5, 99, 0],
+        &[2, 4, 4, 5, 99, 9801],
+    ),
+    halt(
+        "overwrite the halt",
+        &[1, 1, 1, 4, 99, 5, 6, 0, 99],
+        &[30, 1, 1, 4, 2, 5, 6, 0, 99],
+    ),
+];
+
+const EQUAL_POSITION: &[i64] = &[3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+const LESS_POSITION: &[i64] = &[3, 9, 7, 9, 10, 9, 4, 9, 99, -1, 8];
+const EQUAL_IMMEDIATE: &[i64] = &[3, 3, 1108, -1, 8, 3, 4, 3, 99];
+const LESS_IMMEDIATE: &[i64] = &[3, 3, 1107, -1, 8, 3, 4, 3, 99];
+const JUMP_POSITION: &[i64] = &[3, 12, 6, 12, 15, 1, 13, 14, 13, 4, 13, 99, -1, 0, 1, 9];
+const JUMP_IMMEDIATE: &[i64] = &[3, 3, 1105, -1, 9, 1101, 0, 0, 12, 4, 12, 99, 1];
+const COMPARE_TO_8: &[i64] = &[
+    3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0, 0,
+    1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4, 20, 1105,
+    1, 46, 98, 99,
+];
+
+const DAY05: &[Case] = &[
+    run("echo", &[3, 0, 4, 0, 99], &[42], &[42]),
+    halt(
+        "immediate multiply",
+        &[1002, 4, 3, 4, 33],
+        &[1002, 4, 3, 4, 99],
+    ),
+    halt(
+        "negative immediate",
+        &[1101, 100, -1, 4, 0],
+        &[1101, 100, -1, 4, 99],
+    ),
+    run("position equal to 8", EQUAL_POSITION, &[8], &[1]),
+    run("position not equal to 8", EQUAL_POSITION, &[9], &[0]),
+    run("position less than 8", LESS_POSITION, &[7], &[1]),
+    run("position not less than 8", LESS_POSITION, &[8], &[0]),
+    run("immediate equal to 8", EQUAL_IMMEDIATE, &[8], &[1]),
+    run("immediate not equal to 8", EQUAL_IMMEDIATE, &[7], &[0]),
+    run("immediate less than 8", LESS_IMMEDIATE, &[5], &[1]),
+    run("immediate not less than 8", LESS_IMMEDIATE, &[8], &[0]),
+    run("position jump on zero", JUMP_POSITION, &[0], &[0]),
+    run("position jump on non-zero", JUMP_POSITION, &[5], &[1]),
+    run("immediate jump on zero", JUMP_IMMEDIATE, &[0], &[0]),
+    run("immediate jump on non-zero", JUMP_IMMEDIATE, &[5], &[1]),
+    run("below 8", COMPARE_TO_8, &[7], &[999]),
+    run("equal to 8", COMPARE_TO_8, &[8], &[1000]),
+    run("above 8", COMPARE_TO_8, &[9], &[1001]),
+];
+
+const QUINE: &[i64] = &[
+    109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+];
+
+const DAY09: &[Case] = &[
+    run("quine", QUINE, &[], QUINE),
+    run(
+        "16-digit product",
+        &[1102, 34915192, 34915192, 7, 4, 7, 99, 0],
+        &[],
+        &[1219070632396864],
+    ),
+    run(
+        "large number",
+        &[104, 1125899906842624, 99],
+        &[],
+        &[1125899906842624],
+    ),
+];
+
+/// The interpreter without its decode cache.
+struct Uncached<M: Memory>(Cpu<M>);
+
+impl<M: Memory> Machine<M::Cell> for Uncached<M> {
+    fn push_input(&mut self, value: M::Cell) {
+        self.0.push_input(value);
+    }
+
+    fn run(&mut self) -> Result<Status<M::Cell>, IntcodeError> {
+        self.0.run_uncached()
+    }
+}
+
+/// A case's values as cells of type `C`, if they all fit.
+struct Values<C> {
+    program: Vec<C>,
+    input: Vec<C>,
+    output: Vec<C>,
+    memory: Vec<C>,
+}
+
+impl<C: Cell> Values<C> {
+    fn of(case: &Case) -> Option<Self> {
+        let cells = |values: &[i64]| -> Option<Vec<C>> {
+            values.iter().map(|&value| C::from_i64(value)).collect()
+        };
+        Some(Self {
+            program: cells(case.program)?,
+            input: cells(case.input)?,
+            output: cells(case.output)?,
+            memory: cells(case.memory)?,
+        })
+    }
+}
+
+/// Feeds `input` to `machine` and collects its output until it halts.
+fn drive<C: Cell>(machine: &mut impl Machine<C>, input: &[C]) -> Result<Vec<C>, String> {
+    let mut input = input.iter();
+    let mut output = Vec::new();
+    loop {
+        match machine.run().map_err(|e| e.to_string())? {
+            Status::Output(value) => output.push(value),
+            Status::NeedsInput => match input.next() {
+                Some(value) => machine.push_input(value.clone()),
+                None => return Err("ran out of input".to_string()),
+            },
+            Status::Halted => return Ok(output),
+        }
+    }
+}
+
+/// Runs a case on one engine; `memory` is the engine's memory once it's
+/// done.
+fn check<C: Cell, E: Machine<C>>(
+    values: &Values<C>,
+    mut engine: E,
+    memory: impl FnOnce(&E) -> Vec<C>,
+) -> Result<(), String> {
+    let output = drive(&mut engine, &values.input)?;
+    if output != values.output {
+        return Err(format!("output {:?}, expected {:?}", output, values.output));
+    }
+    let memory = memory(&engine);
+    if memory != values.memory {
+        return Err(format!("memory {:?}, expected {:?}", memory, values.memory));
+    }
+    Ok(())
+}
+
+/// The first `len` cells of `mem`.
+fn prefix<M: Memory>(mem: &M, len: usize) -> Vec<M::Cell> {
+    (0..len).map(|addr| mem[addr].clone()).collect()
+}
+
+/// Runs a case on the cached interpreter over `mem`.
+fn check_memory<M: Memory>(values: &Values<M::Cell>, mem: M) -> Result<(), String> {
+    let cpu = Cpu::with_memory(mem, &values.program);
+    check(values, cpu, |cpu| prefix(cpu.mem(), values.memory.len()))
+}
+
+/// Runs a case on every memory backend with cell type `C`, adding the
+/// engines that fail to `failures`.
+fn check_cells<C: Cell>(case: &Case, failures: &mut Vec<String>) {
+    let values = match Values::<C>::of(case) {
+        Some(values) => values,
+        None => return,
+    };
+    let len = values.memory.len();
+    let cell = any::type_name::<C>().rsplit("::").next().unwrap();
+
+    let uncached = Uncached(Cpu::with_memory(Dense::new(), &values.program));
+    let mut code_writes = Cpu::with_memory(Dense::new(), &values.program);
+    code_writes.detect_code_writes(true);
+    let results = vec![
+        ("dense", check_memory(&values, Dense::new())),
+        ("sparse", check_memory(&values, Sparse::new())),
+        ("hybrid", check_memory(&values, Hybrid::new())),
+        // Low enough that the examples spill into the sparse part.
+        ("hybrid(4)", check_memory(&values, Hybrid::with_limit(4))),
+        (
+            "uncached",
+            check(&values, uncached, |cpu| prefix(cpu.0.mem(), len)),
+        ),
+        (
+            "code-writes",
+            check(&values, code_writes, |cpu| prefix(cpu.mem(), len)),
+        ),
+    ];
+
+    for (engine, result) in results {
+        if let Err(e) = result {
+            failures.push(format!("{} on {}<{}>: {}", case.name, engine, cell, e));
+        }
+    }
+}
+
+#[cfg(all(feature = "jit", target_arch = "x86_64", target_os = "linux"))]
+fn check_jit(case: &Case, failures: &mut Vec<String>) {
+    let values = Values::<i64>::of(case).expect("the examples are i64 programs");
+    let jit = intcode::jit::Jit::new(&values.program);
+    if let Err(e) = check(&values, jit, |jit| {
+        prefix(jit.cpu().mem(), values.memory.len())
+    }) {
+        failures.push(format!("{} on jit: {}", case.name, e));
+    }
+}
+
+#[cfg(not(all(feature = "jit", target_arch = "x86_64", target_os = "linux")))]
+fn check_jit(_: &Case, _: &mut Vec<String>) {}
+
+fn conform(cases: &[Case]) {
+    let mut failures = Vec::new();
+    for case in cases {
+        check_cells::<i32>(case, &mut failures);
+        check_cells::<i64>(case, &mut failures);
+        check_cells::<i128>(case, &mut failures);
+        check_cells::<usize>(case, &mut failures);
+        check_cells::<BigInt>(case, &mut failures);
+        check_jit(case, &mut failures);
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn day02_examples() {
+    conform(DAY02);
+}
+
+#[test]
+fn day05_examples() {
+    conform(DAY05);
+}
+
+#[test]
+fn day09_examples() {
+    conform(DAY09);
+}
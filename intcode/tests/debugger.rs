@@ -0,0 +1,41 @@
+//! Debugger commands that take addresses from the user.
+
+use intcode::debugger::Debugger;
+use intcode::Cpu;
+
+fn command(dbg: &mut Debugger, line: &str) -> String {
+    let mut out = Vec::new();
+    assert!(dbg.command(line, &mut out).unwrap());
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn dumps_memory() {
+    let mut dbg = Debugger::new(Cpu::new(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]));
+    assert_eq!(command(&mut dbg, "x 1 3"), "     1: 2 3 4\n");
+    assert_eq!(
+        command(&mut dbg, "x 0 10"),
+        "     0: 1 2 3 4 5 6 7 8\n     8: 9 10\n"
+    );
+}
+
+#[test]
+fn rejects_dumps_out_of_range() {
+    let mut dbg = Debugger::new(Cpu::new(&[99]));
+    assert_eq!(
+        command(&mut dbg, "x 0 100000"),
+        "at most 4096 words at a time\n"
+    );
+    assert_eq!(command(&mut dbg, "x 0 -1"), "usage: x <addr> [len]\n");
+    let far = i64::MAX - 2;
+    assert_eq!(
+        command(&mut dbg, &format!("x {} 8", far)),
+        format!("{:>6}: 0 0 0 0 0 0 0 0\n", far)
+    );
+    assert_eq!(
+        command(&mut dbg, &format!("dis {} 1000000", far))
+            .lines()
+            .count(),
+        4096
+    );
+}
@@ -0,0 +1,43 @@
+//! JIT cases the conformance examples don't reach.
+#![cfg(all(feature = "jit", target_arch = "x86_64", target_os = "linux"))]
+
+use intcode::jit::Jit;
+use intcode::{Machine, Status};
+
+/// Clones share compiled blocks, but not memory. A block compiled while
+/// one clone's memory reached past address 100000 must not store there
+/// unchecked for a clone whose memory never grew.
+#[test]
+fn clones_with_shorter_memory() {
+    let mut program = vec![
+        3, 50, 1006, 50, 9, 1101, 0, 0, 100000, 1101, 7, 0, 100000, 4, 100000, 99,
+    ];
+    program.resize(60, 0);
+    let jit = Jit::new(&program);
+
+    let mut grown = jit.clone();
+    grown.push_input(1);
+    assert_eq!(grown.run(), Ok(Status::Output(7)));
+
+    let mut short = jit.clone();
+    short.push_input(0);
+    assert_eq!(short.run(), Ok(Status::Output(7)));
+    assert_eq!(short.run(), Ok(Status::Halted));
+}
+
+/// Array indexing patches the address operands of later instructions,
+/// which compiled blocks read from memory instead of baking in.
+#[test]
+fn patched_address_operands() {
+    let mut program = vec![
+        1101, 0, 0, 40, 1001, 40, 100, 11, 1001, 40, 0, 0, 1001, 40, 1, 40, 1007, 40, 5, 41, 1005,
+        41, 4, 1101, 103, 0, 28, 1001, 0, 0, 42, 4, 42, 99,
+    ];
+    program.resize(110, 0);
+
+    let mut jit = Jit::new(&program);
+    assert_eq!(jit.run(), Ok(Status::Output(3)));
+    assert_eq!(jit.run(), Ok(Status::Halted));
+    let array: Vec<_> = (100..105).map(|addr| jit.cpu().mem()[addr]).collect();
+    assert_eq!(array, [0, 1, 2, 3, 4]);
+}
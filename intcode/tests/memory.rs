@@ -0,0 +1,33 @@
+//! Stores past what a memory backend can hold fault instead of allocating.
+
+use intcode::{Cpu, Dense, ErrorKind, Hybrid, Sparse, Status};
+
+const HUGE: &[i64] = &[1101, 0, 0, 100_000_000_000, 99];
+const HUGER: &[i64] = &[1101, 0, 0, 4_000_000_000_000_000_000, 99];
+
+#[test]
+fn dense_rejects_stores_past_its_limit() {
+    for program in &[HUGE, HUGER] {
+        let mut cpu = Cpu::new(program);
+        let err = cpu.clone().run().unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidAddress(program[3]));
+        assert_eq!(err.pc, 0);
+        assert_eq!(cpu.run_uncached().unwrap_err(), err);
+    }
+
+    let mut cpu = Cpu::with_memory(Dense::with_limit(8), &[21101, 7, 0, 8, 99]);
+    assert_eq!(cpu.run().unwrap_err().kind, ErrorKind::InvalidAddress(8));
+}
+
+#[test]
+fn sparse_backends_take_huge_stores() {
+    let program: [i64; 5] = [1101, 3, 4, 100_000_000_000, 99];
+
+    let mut cpu = Cpu::with_memory(Sparse::new(), &program);
+    assert_eq!(cpu.run(), Ok(Status::Halted));
+    assert_eq!(cpu.mem()[100_000_000_000], 7);
+
+    let mut cpu = Cpu::with_memory(Hybrid::new(), &program);
+    assert_eq!(cpu.run(), Ok(Status::Halted));
+    assert_eq!(cpu.mem()[100_000_000_000], 7);
+}
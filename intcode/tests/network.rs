@@ -0,0 +1,83 @@
+//! Networks of machines, scheduled on one thread and on many.
+
+use intcode::network::{
+    Event, Network, Route, Scheduler, Target, Threaded, Topology, TopologyError,
+};
+use intcode::Cpu;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// Outputs one more than each number it reads.
+const INCREMENT: &[i64] = &[3, 11, 1001, 11, 1, 11, 4, 11, 1105, 1, 0, 0];
+
+/// Reads `a` and sends `a + 1` to address `a + 1`, as a packet of two.
+const FORWARD: &[i64] = &[3, 20, 1001, 20, 1, 21, 4, 21, 4, 21, 1105, 1, 0];
+
+fn events(network: &mut impl Scheduler) -> Vec<Event> {
+    let mut events = Vec::new();
+    loop {
+        match network.run().unwrap() {
+            Event::Idle => return events,
+            event => events.push(event),
+        }
+    }
+}
+
+fn outputs(values: &[i64]) -> Vec<Event> {
+    let output = |&value| Event::Output { from: 2, value };
+    values.iter().map(output).collect()
+}
+
+fn pipeline(mut network: impl Scheduler) {
+    assert_eq!(events(&mut network), outputs(&[3, 13]));
+    network.send(0, &[5]);
+    assert_eq!(events(&mut network), outputs(&[8]));
+}
+
+#[test]
+fn pipelines_pass_values_along() {
+    let cpu = Cpu::new(INCREMENT);
+    let topology = || Topology::pipeline(3).input(0, &[0, 10]);
+    pipeline(Network::new(topology(), &cpu).unwrap());
+    pipeline(Threaded::new(topology(), &cpu, 1).unwrap());
+}
+
+#[test]
+fn bus_packets_leave_for_missing_addresses() {
+    let cpu = Cpu::new(FORWARD);
+    let topology = || Topology::bus(2, 2).input(0, &[0]);
+    let packets = vec![Event::Packet {
+        from: 1,
+        addr: 2,
+        words: vec![2],
+    }];
+    let mut network = Network::new(topology(), &cpu).unwrap();
+    assert_eq!(events(&mut network), packets);
+    let mut threaded = Threaded::new(topology(), &cpu, 1).unwrap();
+    assert_eq!(events(&mut threaded), packets);
+}
+
+#[test]
+fn rejects_routes_to_missing_nodes() {
+    let cpu = Cpu::new(INCREMENT);
+    let topology = || Topology::pipeline(2).route(1, Route::Send(vec![Target::Node(2)]));
+    let err = TopologyError { node: 1, to: 2 };
+    assert_eq!(topology().validate(), Err(err));
+    assert_eq!(Network::new(topology(), &cpu).err(), Some(err));
+    assert_eq!(Threaded::new(topology(), &cpu, 1).err(), Some(err));
+}
+
+/// Dropping the network stops nodes partway through a computation, not
+/// just at their next input or output.
+#[test]
+fn dropping_threads_stops_busy_nodes() {
+    let spin = Cpu::new(&[1105, 1, 0]);
+    let threaded = Threaded::new(Topology::new(2), &spin, 1).unwrap();
+    let (dropped, done) = mpsc::channel();
+    thread::spawn(move || {
+        drop(threaded);
+        dropped.send(()).unwrap();
+    });
+    assert_eq!(done.recv_timeout(Duration::from_secs(10)), Ok(()));
+}
@@ -0,0 +1,76 @@
+//! Recording sessions, saving the log and replaying it.
+
+use intcode::record::{self, Entry, Log, Recorder, ReplayError};
+use intcode::{Cpu, Status};
+
+/// Reads numbers and outputs their running total, kept at 21.
+const SUMMER: &[i64] = &[3, 20, 1, 20, 21, 21, 4, 21, 1105, 1, 0, 0];
+
+fn program() -> Cpu {
+    let mut program = SUMMER.to_vec();
+    program.resize(22, 0);
+    Cpu::new(&program)
+}
+
+fn session() -> Log {
+    let mut recorder = Recorder::new(program());
+    recorder.poke(21, 100);
+    let mut outputs = Vec::new();
+    for value in 1..=3 {
+        recorder.push_input(value);
+        while let Status::Output(value) = recorder.run().unwrap() {
+            outputs.push(value);
+        }
+    }
+    assert_eq!(outputs, [101, 103, 106]);
+    recorder.into_log()
+}
+
+fn reload(log: &Log) -> Log {
+    let mut text = Vec::new();
+    log.save(&mut text).unwrap();
+    Log::load(text.as_slice()).unwrap()
+}
+
+#[test]
+fn saved_sessions_replay() {
+    let log = session();
+    assert_eq!(
+        log.entries[0],
+        Entry::Poke {
+            step: 0,
+            addr: 21,
+            value: 100
+        }
+    );
+    let log = reload(&log);
+    assert_eq!(log, session());
+    record::replay(program(), &log).unwrap();
+}
+
+#[test]
+fn replays_catch_divergence() {
+    let mut log = session();
+    let last = log.entries.len() - 1;
+    log.entries[last] = match log.entries[last] {
+        Entry::Out { step, value } => Entry::Out {
+            step,
+            value: value + 1,
+        },
+        entry => panic!("unexpected {}", entry),
+    };
+    match record::replay(program(), &log) {
+        Err(ReplayError::Diverged { index, .. }) => assert_eq!(index, last),
+        result => panic!("unexpected {:?}", result),
+    }
+
+    let mut log = session();
+    log.steps += 1;
+    assert!(matches!(
+        record::replay(program(), &log),
+        Err(ReplayError::Steps {
+            expected: 13,
+            found: 12
+        })
+    ));
+}
@@ -0,0 +1,102 @@
+//! Saving a CPU partway through a run and restoring it somewhere else.
+
+use intcode::snapshot::SnapshotError;
+use intcode::{Cpu, Dense, Hybrid, Machine, Memory, Overflow, Sparse, Status};
+
+/// Reads numbers and outputs their running total, kept past the end of
+/// the program and read back through the relative base.
+const ADDER: &[i64] = &[109, 5000, 3, 20, 1, 20, 21, 21, 204, -4979, 1105, 1, 2];
+
+fn outputs(cpu: &mut impl Machine, input: &[i64]) -> Vec<i64> {
+    let mut outputs = Vec::new();
+    for &value in input {
+        cpu.push_input(value);
+        while let Status::Output(value) = cpu.run().unwrap() {
+            outputs.push(value);
+        }
+    }
+    outputs
+}
+
+fn save(cpu: &Cpu<impl Memory<Cell = i64>>) -> String {
+    let mut text = Vec::new();
+    cpu.save(&mut text).unwrap();
+    String::from_utf8(text).unwrap()
+}
+
+#[test]
+fn resumes_where_it_was_saved() {
+    let mut cpu = Cpu::new(ADDER);
+    cpu.set_overflow(Overflow::Wrap);
+    assert_eq!(outputs(&mut cpu, &[1, 2, 3]), [1, 3, 6]);
+    cpu.push_input(10);
+    let text = save(&cpu);
+
+    let mut restored = Cpu::<Dense>::restore(text.as_bytes()).unwrap();
+    assert_eq!(restored.pc(), cpu.pc());
+    assert_eq!(restored.relative_offset(), cpu.relative_offset());
+    assert_eq!(restored.pending_input(), cpu.pending_input());
+    assert_eq!(restored.overflow(), Overflow::Wrap);
+    assert_eq!(restored.mem().cells(), cpu.mem().cells());
+    assert_eq!(save(&restored), text);
+
+    let expected = outputs(&mut cpu, &[20, 30]);
+    assert_eq!(expected, [16, 36, 66]);
+    assert_eq!(outputs(&mut restored, &[20, 30]), expected);
+}
+
+#[test]
+fn restores_into_any_backend() {
+    let mut cpu = Cpu::new(ADDER);
+    outputs(&mut cpu, &[4, 5]);
+    let text = save(&cpu);
+
+    let mut sparse = Cpu::<Sparse>::restore(text.as_bytes()).unwrap();
+    let mut hybrid = Cpu::<Hybrid>::restore(text.as_bytes()).unwrap();
+    assert_eq!(save(&sparse), text);
+    assert_eq!(outputs(&mut sparse, &[6]), [15]);
+    assert_eq!(outputs(&mut hybrid, &[6]), [15]);
+}
+
+fn parse_error(text: &str) -> (usize, String) {
+    match Cpu::<Dense>::restore(text.as_bytes()) {
+        Err(SnapshotError::Parse { line, message }) => (line, message),
+        Err(e) => panic!("unexpected error: {}", e),
+        Ok(_) => panic!("restored {:?}", text),
+    }
+}
+
+#[test]
+fn rejects_addresses_memory_cannot_hold() {
+    let header = "intcode-snapshot 1\npc 0\n";
+    let overflowing = format!("{}mem {} 1,2\n", header, usize::MAX);
+    assert_eq!(
+        parse_error(&overflowing),
+        (3, "address out of range".into())
+    );
+    let huge = format!("{}mem 100000000000 1\n", header);
+    assert_eq!(parse_error(&huge), (3, "address out of range".into()));
+
+    let cpu = Cpu::<Sparse>::restore(huge.as_bytes()).unwrap();
+    assert_eq!(cpu.mem()[100_000_000_000], 1);
+}
+
+#[test]
+fn rejects_malformed_snapshots() {
+    assert_eq!(
+        parse_error("hello\n"),
+        (1, "not an intcode snapshot".into())
+    );
+    assert_eq!(
+        parse_error("intcode-snapshot 1\npc -1\n"),
+        (2, "invalid pc".into())
+    );
+    assert_eq!(
+        parse_error("intcode-snapshot 1\nmem 0 1,x\n"),
+        (2, "invalid value".into())
+    );
+    assert!(matches!(
+        Cpu::<Dense>::restore("intcode-snapshot 2\n".as_bytes()),
+        Err(SnapshotError::UnsupportedVersion(v)) if v == "2"
+    ));
+}
@@ -0,0 +1,37 @@
+//! Symbolic execution of day 2 style programs.
+
+use intcode::symbolic::{Execution, SymbolicError};
+
+/// `mem[0] = mem[9] * mem[10] + mem[9]`, with both of those unknown.
+const PROGRAM: &[i64] = &[2, 9, 10, 0, 1, 0, 9, 0, 99, 0, 0];
+
+#[test]
+fn solves_for_the_unknowns() {
+    let exec = Execution::run(PROGRAM, &[9, 10]).unwrap();
+    let output = exec.formula(0);
+    assert_eq!(output.eval(&[3, 4]), Some(15));
+    assert!(!output.is_linear());
+    assert_eq!(
+        output.solve(12, 0..=5),
+        [vec![2, 5], vec![3, 3], vec![4, 2]]
+    );
+}
+
+#[test]
+fn too_few_values_evaluate_to_nothing() {
+    let exec = Execution::run(PROGRAM, &[9, 10]).unwrap();
+    assert_eq!(exec.formula(0).eval(&[3]), None);
+}
+
+#[test]
+fn rejects_stores_past_memory() {
+    let far = 100_000_000_000;
+    match Execution::run(&[1, 0, 0, far, 99], &[]) {
+        Err(SymbolicError::InvalidAddress { pc: 0, addr }) => assert_eq!(addr, far),
+        result => panic!("unexpected {:?}", result.err()),
+    }
+    match Execution::run(&[99], &[1 << 40]) {
+        Err(SymbolicError::InvalidAddress { pc: 0, addr }) => assert_eq!(addr, 1 << 40),
+        result => panic!("unexpected {:?}", result.err()),
+    }
+}